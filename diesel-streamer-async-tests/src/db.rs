@@ -0,0 +1,62 @@
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use dotenvy::dotenv;
+use std::env;
+
+use crate::migrations;
+
+/// # Panics
+pub async fn establish_connection() -> AsyncPgConnection {
+    connect_to_database_url_or_panic(&database_url()).await
+}
+
+/// # Panics
+pub async fn setup() -> AsyncPgConnection {
+    let db_url = database_url();
+
+    match AsyncPgConnection::establish(&db_url).await {
+        Ok(conn) => conn,
+        Err(_error) => {
+            let (db_name, db_raw_url) = get_db_name_and_raw_url(&db_url);
+
+            let mut raw_conn = connect_to_database_url_or_panic(&db_raw_url).await;
+
+            create_database(&db_name, &mut raw_conn).await;
+
+            let mut conn = establish_connection().await;
+
+            migrations::run(&mut conn).await;
+
+            conn
+        }
+    }
+}
+
+fn database_url() -> String {
+    dotenv().ok();
+
+    env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL env variable needs to be set.")
+}
+
+fn get_db_name_and_raw_url(url: &str) -> (String, String) {
+    let mut url_split = url.split('/').collect::<Vec<&str>>();
+
+    let db_name = url_split
+        .pop()
+        .expect("DATABASE NAME needs to be specified. See: sample.env");
+    let db_raw_url = url_split.join("/");
+
+    (db_name.to_string(), db_raw_url)
+}
+
+async fn create_database(db_name: &str, conn: &mut AsyncPgConnection) {
+    diesel::sql_query(format!(r#"CREATE DATABASE "{db_name}""#))
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+async fn connect_to_database_url_or_panic(db_url: &str) -> AsyncPgConnection {
+    AsyncPgConnection::establish(db_url)
+        .await
+        .unwrap_or_else(|_| panic!("Error connecting to {db_url}"))
+}
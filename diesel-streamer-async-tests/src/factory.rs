@@ -0,0 +1,50 @@
+use diesel::{prelude::*, Insertable};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_streamer::Streamable;
+use serde::Serialize;
+
+// User Factory
+
+table! {
+  users (id) {
+      id -> Serial,
+      name -> Varchar,
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = users)]
+pub struct UnsavedUser {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Serialize, Streamable)]
+#[streamable(table = users, cursor = id, mode = async)]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+}
+
+/// # Panics
+pub async fn insert_users(number_of_users: u16, conn: &mut AsyncPgConnection) {
+    use self::users::dsl::users;
+
+    let unsaved_users: Vec<UnsavedUser> = (1..=number_of_users)
+        .map(|index| UnsavedUser {
+            name: format!("UserName {index}"),
+        })
+        .collect();
+
+    diesel::insert_into(users)
+        .values(unsaved_users)
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+/// # Panics
+pub async fn get_users(conn: &mut AsyncPgConnection) -> Vec<User> {
+    use self::users::dsl::{id, users};
+
+    users.order_by(id).get_results::<User>(conn).await.unwrap()
+}
@@ -0,0 +1,5 @@
+pub mod db;
+pub mod factory;
+mod migrations;
+pub mod test_runner;
+mod tests;
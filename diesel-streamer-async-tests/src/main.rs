@@ -0,0 +1,8 @@
+use diesel_streamer_async_tests::db;
+
+#[tokio::main]
+async fn main() {
+    // Run once to setup database
+    // Useful in a CI environment running parallel tests
+    let _connection_after_setup = db::setup().await;
+}
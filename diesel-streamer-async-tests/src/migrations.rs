@@ -0,0 +1,21 @@
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+pub async fn run(conn: &mut AsyncPgConnection) {
+    create_user_table(conn).await;
+}
+
+async fn create_user_table(conn: &mut AsyncPgConnection) {
+    diesel::sql_query(
+        r"
+        CREATE TABLE IF NOT EXISTS users (
+            id SERIAL PRIMARY KEY,
+            name VARCHAR NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+    ",
+    )
+    .execute(conn)
+    .await
+    .unwrap();
+}
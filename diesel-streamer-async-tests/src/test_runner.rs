@@ -0,0 +1,28 @@
+use std::env;
+
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use dotenvy::dotenv;
+
+use crate::db;
+
+/// Connects to the test database inside a transaction that is rolled back
+/// once the connection is dropped.
+///
+/// # Panics
+pub async fn test_connection() -> AsyncPgConnection {
+    let mut conn = if should_skip_db_setup() {
+        db::establish_connection().await
+    } else {
+        db::setup().await
+    };
+
+    conn.begin_test_transaction().await.unwrap();
+
+    conn
+}
+
+fn should_skip_db_setup() -> bool {
+    dotenv().ok();
+
+    env::var("SKIP_DB_SETUP").is_ok()
+}
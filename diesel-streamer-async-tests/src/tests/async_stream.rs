@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use diesel_async::AsyncPgConnection;
+    use futures_util::StreamExt;
+    use tokio::sync::Mutex;
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn streams_table_data_in_chunks() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(5, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+        let conn = Arc::new(Mutex::new(conn));
+
+        let chunks = diesel_streamer::get_serial_table_async_stream!(
+            users,
+            id,
+            conn,
+            Arc<Mutex<AsyncPgConnection>>,
+            User,
+            i32,
+            2
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(
+            chunks,
+            vec![
+                all_users[0..2].to_vec(),
+                all_users[2..4].to_vec(),
+                all_users[4..].to_vec()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_between_the_given_bounds() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(5, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+        let conn = Arc::new(Mutex::new(conn));
+
+        let chunks = diesel_streamer::get_serial_table_async_stream!(
+            users,
+            id,
+            conn,
+            Arc<Mutex<AsyncPgConnection>>,
+            User,
+            i32,
+            10,
+            Some(all_users[1].id),
+            Some(all_users[3].id)
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(chunks.concat(), all_users[1..=3]);
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use diesel_streamer::{BatchEnd, StreamEnd, StreamOptions};
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn updates_every_row_in_chunks() {
+        use factory::users::dsl::{id, name, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(5, conn).await;
+
+        let last_user = factory::get_users(conn).await.pop().unwrap();
+
+        let batch_end = diesel_streamer::r#async::update_in_batches!(
+            users,
+            id,
+            conn,
+            name.eq("Anonymous"),
+            options = StreamOptions::new().chunk_size(2)
+        )
+        .unwrap();
+
+        assert_eq!(
+            batch_end,
+            BatchEnd {
+                affected_rows: 5,
+                stream_end: StreamEnd::Completed {
+                    cursor: Some(last_user.id + 1)
+                }
+            }
+        );
+        assert!(factory::get_users(conn)
+            .await
+            .iter()
+            .all(|user| user.name == "Anonymous"));
+    }
+
+    #[tokio::test]
+    async fn passes_deleted_rows_to_the_processor() {
+        use factory::users::dsl::{id, name, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(5, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+
+        let mut archived_users = vec![];
+
+        let batch_end = diesel_streamer::r#async::delete_in_batches!(
+            users.filter(name.ne("UserName 2")),
+            id,
+            conn,
+            options = StreamOptions::new().chunk_size(2),
+            |deleted_users: Vec<User>| {
+                archived_users.extend(deleted_users);
+                async {}
+            }
+        )
+        .unwrap();
+
+        assert_eq!(batch_end.affected_rows, 4);
+        assert_eq!(archived_users.len(), 4);
+        assert_eq!(factory::get_users(conn).await, all_users[1..2]);
+    }
+}
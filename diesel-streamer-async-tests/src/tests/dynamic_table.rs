@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use diesel_streamer::{DynamicRow, StreamEnd, StreamOptions};
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn streams_a_table_known_by_name() {
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(3, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+
+        let mut streamed_rows = vec![];
+
+        let stream_end = diesel_streamer::r#async::stream_dynamic_table!(
+            "users",
+            conn,
+            options = StreamOptions::new().chunk_size(2),
+            |rows: Vec<DynamicRow>| {
+                streamed_rows.extend(rows);
+                async {}
+            }
+        )
+        .unwrap();
+
+        assert_eq!(
+            stream_end,
+            StreamEnd::Completed {
+                cursor: Some(i64::from(all_users[2].id) + 1)
+            }
+        );
+        assert_eq!(
+            streamed_rows
+                .iter()
+                .map(|row| (row["id"].clone(), row["name"].clone()))
+                .collect::<Vec<_>>(),
+            all_users
+                .iter()
+                .map(|user| (user.id.into(), user.name.clone().into()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
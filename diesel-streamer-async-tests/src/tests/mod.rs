@@ -0,0 +1,6 @@
+mod async_stream;
+mod batches;
+mod dynamic_table;
+mod serial_table;
+mod sinks;
+mod streamable;
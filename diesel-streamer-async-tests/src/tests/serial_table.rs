@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use diesel_streamer::{StreamEnd, StreamOptions};
+    use tokio_util::sync::CancellationToken;
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn allows_processing_table_data_in_chunks() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(5, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+
+        let mut chunks = vec![];
+
+        let stream_end = diesel_streamer::r#async::stream_serial_table!(
+            users,
+            id,
+            conn,
+            2,
+            |loaded_users: Vec<User>| {
+                chunks.push(loaded_users);
+                async {}
+            }
+        );
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), all_users);
+        assert_eq!(
+            stream_end,
+            StreamEnd::Completed {
+                cursor: Some(all_users[4].id + 1)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_between_the_given_bounds() {
+        use factory::users::dsl::{id, name, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(5, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+
+        let mut names = vec![];
+
+        diesel_streamer::r#async::stream_serial_table!(
+            users,
+            id,
+            conn,
+            select = name,
+            options = StreamOptions::new()
+                .from(all_users[1].id)
+                .to(all_users[3].id),
+            |chunk: Vec<String>| {
+                names.extend(chunk);
+                async {}
+            }
+        );
+
+        assert_eq!(names, ["UserName 2", "UserName 3", "UserName 4"]);
+    }
+
+    #[tokio::test]
+    async fn stops_on_the_processor_break() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(5, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+
+        let stream_end = diesel_streamer::r#async::stream_serial_table!(
+            users.filter(id.ne(all_users[0].id)),
+            id,
+            conn,
+            options = StreamOptions::new().chunk_size(1),
+            |loaded_users: Vec<User>| async move {
+                if loaded_users[0].name == "UserName 3" {
+                    ControlFlow::Break(loaded_users[0].id)
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        );
+
+        assert_eq!(
+            stream_end,
+            StreamEnd::Stopped {
+                cursor: Some(all_users[2].id),
+                reason: all_users[2].id
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_between_chunks_once_cancelled() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+        let conn = &mut conn;
+
+        factory::insert_users(3, conn).await;
+
+        let all_users = factory::get_users(conn).await;
+        let cancellation = CancellationToken::new();
+
+        let mut call_count = 0;
+
+        let stream_end = diesel_streamer::r#async::stream_serial_table!(
+            users,
+            id,
+            conn,
+            options = StreamOptions::new()
+                .chunk_size(1)
+                .cancellation(cancellation.clone()),
+            |_loaded_users: Vec<User>| {
+                call_count += 1;
+                cancellation.cancel();
+                async {}
+            }
+        );
+
+        assert_eq!(call_count, 1);
+        assert_eq!(
+            stream_end,
+            StreamEnd::Cancelled {
+                cursor: Some(all_users[1].id)
+            }
+        );
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use diesel_async::AsyncPgConnection;
+    use diesel_streamer::{AsyncCsvSink, AsyncJsonLinesSink};
+    use futures_util::StreamExt;
+    use tokio::sync::Mutex;
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn writes_streamed_chunks_as_csv() {
+        use factory::users::dsl::{id, users};
+
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(3, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+        let conn = Arc::new(Mutex::new(conn));
+
+        let mut stream = diesel_streamer::get_serial_table_async_stream!(
+            users,
+            id,
+            conn,
+            Arc<Mutex<AsyncPgConnection>>,
+            User,
+            i32,
+            2
+        );
+        let mut sink = AsyncCsvSink::new(vec![]);
+
+        while let Some(chunk) = stream.next().await {
+            sink.write_chunk(&chunk).await.unwrap();
+        }
+
+        sink.flush().await.unwrap();
+
+        let expected_csv = format!(
+            "id,name\n{},UserName 1\n{},UserName 2\n{},UserName 3\n",
+            all_users[0].id, all_users[1].id, all_users[2].id
+        );
+
+        assert_eq!(String::from_utf8(sink.into_inner()).unwrap(), expected_csv);
+    }
+
+    #[tokio::test]
+    async fn drops_the_rows_of_a_chunk_that_failed_to_serialize() {
+        let mut sink = AsyncCsvSink::new(vec![]);
+
+        sink.write_chunk(&[(1, "Ada Lovelace")]).await.unwrap();
+        // the second row has a nested map, which a CSV record cannot hold
+        sink.write_chunk(&[
+            serde_json::json!([2, "Alan Turing"]),
+            serde_json::json!([3, { "name": "Grace Hopper" }]),
+        ])
+        .await
+        .unwrap_err();
+        sink.write_chunk(&[(4, "Edsger Dijkstra")]).await.unwrap();
+
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            "1,Ada Lovelace\n4,Edsger Dijkstra\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_one_json_object_per_line() {
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(3, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+
+        let mut stream = User::async_stream(Arc::new(Mutex::new(conn)));
+        let mut sink = AsyncJsonLinesSink::new(vec![]);
+
+        while let Some(chunk) = stream.next().await {
+            sink.write_chunk(&chunk).await.unwrap();
+        }
+
+        let expected_json_lines = all_users
+            .iter()
+            .map(|user| serde_json::json!({ "id": user.id, "name": user.name }).to_string() + "\n")
+            .collect::<String>();
+
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            expected_json_lines
+        );
+    }
+}
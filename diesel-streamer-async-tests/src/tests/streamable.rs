@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+    use std::sync::Arc;
+
+    use diesel_streamer::{StreamEnd, StreamOptions};
+    use futures_util::StreamExt;
+    use tokio::sync::Mutex;
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[tokio::test]
+    async fn streams_the_declared_table_by_the_declared_cursor() {
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(5, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+
+        let mut streamed_users = vec![];
+
+        let stream_end = User::stream(&mut conn)
+            .chunk_size(2)
+            .from(all_users[1].id)
+            .for_each(|users| {
+                streamed_users.extend(users);
+                async {}
+            })
+            .await;
+
+        assert_eq!(streamed_users, all_users[1..]);
+        assert_eq!(
+            stream_end,
+            StreamEnd::Completed {
+                cursor: Some(all_users[4].id + 1)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_on_the_processor_break() {
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(5, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+
+        let stream_end = User::stream(&mut conn)
+            .options(StreamOptions::new().chunk_size(2))
+            .for_each(|users| async move {
+                match users.iter().find(|user| user.name == "UserName 4") {
+                    Some(user) => ControlFlow::Break(user.id),
+                    None => ControlFlow::Continue(()),
+                }
+            })
+            .await;
+
+        assert_eq!(
+            stream_end,
+            StreamEnd::Stopped {
+                cursor: Some(all_users[2].id),
+                reason: all_users[3].id
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_an_async_stream_of_chunks() {
+        let mut conn = test_runner::test_connection().await;
+
+        factory::insert_users(3, &mut conn).await;
+
+        let all_users = factory::get_users(&mut conn).await;
+
+        let chunks = User::async_stream(Arc::new(Mutex::new(conn)))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(chunks.concat(), all_users);
+    }
+}
@@ -1,4 +1,5 @@
 use diesel::{pg::PgConnection, prelude::*, Insertable};
+use diesel_streamer::Streamable;
 
 // User Factory
 
@@ -17,7 +18,8 @@ pub struct UnsavedUser {
     pub name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Streamable)]
+#[streamable(table = users, cursor = id)]
 #[allow(dead_code)]
 pub struct User {
     pub id: i32,
@@ -26,6 +28,13 @@ pub struct User {
     pub updated_at: chrono::NaiveDateTime,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Selectable)]
+#[diesel(table_name = users)]
+pub struct UserName {
+    pub id: i32,
+    pub name: String,
+}
+
 /// # Panics
 #[allow(clippy::uninlined_format_args)]
 pub fn insert_users(number_of_users: u16, conn: &mut PgConnection) {
@@ -72,3 +81,111 @@ pub fn get_user_by_name(user_name: &str, conn: &mut PgConnection) -> Option<User
         .optional()
         .unwrap()
 }
+
+// User Copy Factory
+
+table! {
+  user_copies (id) {
+      id -> Int4,
+      name -> Varchar,
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_copies)]
+pub struct NewUserCopy {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct UserCopy {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Creates `user_copies` for the current session only.
+///
+/// # Panics
+pub fn create_user_copies_table(conn: &mut PgConnection) {
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE user_copies (id INTEGER PRIMARY KEY, name VARCHAR NOT NULL)",
+    )
+    .execute(conn)
+    .unwrap();
+}
+
+/// # Panics
+pub fn get_user_copies(conn: &mut PgConnection) -> Vec<UserCopy> {
+    use self::user_copies::dsl::{id, user_copies};
+
+    user_copies
+        .order_by(id)
+        .get_results::<UserCopy>(conn)
+        .unwrap()
+}
+
+// Post Factory
+
+table! {
+  posts (id) {
+      id -> Int4,
+      user_id -> Nullable<Int4>,
+      title -> Varchar,
+  }
+}
+
+joinable!(posts -> users (user_id));
+allow_tables_to_appear_in_same_query!(posts, users);
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = posts)]
+pub struct NewPost {
+    pub user_id: Option<i32>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct Post {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub title: String,
+}
+
+/// Creates `posts` for the current session only.
+///
+/// # Panics
+pub fn create_posts_table(conn: &mut PgConnection) {
+    diesel::sql_query(
+        "CREATE TEMPORARY TABLE posts (id SERIAL PRIMARY KEY, user_id INTEGER, title VARCHAR NOT NULL)",
+    )
+    .execute(conn)
+    .unwrap();
+}
+
+/// Inserts a post per user, plus a post without a user.
+///
+/// # Panics
+pub fn insert_posts(conn: &mut PgConnection) -> Vec<Post> {
+    use self::posts::dsl::posts;
+
+    let mut new_posts: Vec<NewPost> = get_users(conn)
+        .into_iter()
+        .map(|user| NewPost {
+            user_id: Some(user.id),
+            title: format!("Post by {}", user.name),
+        })
+        .collect();
+    new_posts.insert(
+        1,
+        NewPost {
+            user_id: None,
+            title: "Orphan post".to_string(),
+        },
+    );
+
+    diesel::insert_into(posts)
+        .values(new_posts)
+        .get_results(conn)
+        .unwrap()
+}
@@ -6,14 +6,14 @@ pub fn run(conn: &mut PgConnection) {
 
 fn create_user_table(conn: &mut PgConnection) {
     sql_query(
-        r#"
+        r"
         CREATE TABLE IF NOT EXISTS users (
             id SERIAL PRIMARY KEY,
             name VARCHAR NOT NULL,
             created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
             updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
         )
-    "#,
+    ",
     )
     .execute(conn)
     .unwrap();
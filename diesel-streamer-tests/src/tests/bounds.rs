@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel_streamer::{StreamEnd, StreamOptions};
+
+    use crate::factory::{self, NewUserCopy, User, UserCopy};
+    use crate::test_runner;
+
+    fn insert_user_copies(ids: &[i32], conn: &mut PgConnection) {
+        use factory::user_copies::dsl::user_copies;
+
+        factory::create_user_copies_table(conn);
+
+        diesel::insert_into(user_copies)
+            .values(
+                ids.iter()
+                    .map(|&id| NewUserCopy {
+                        id,
+                        name: format!("Copy {id}"),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[test]
+    fn estimates_bounds_from_the_sequence() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+            let last_user = all_users.last().unwrap();
+
+            // MAX(id) falls behind the sequence's last value
+            diesel::delete(users.filter(id.eq(last_user.id)))
+                .execute(conn)
+                .unwrap();
+
+            let mut loaded_users = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().estimated_bounds(),
+                |chunk: Vec<User>| loaded_users.extend(chunk)
+            );
+
+            assert_eq!(loaded_users, all_users[..2]);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(last_user.id + 1)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn ignores_histograms_without_a_sequence() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            insert_user_copies(&[-4, 2, 7, 9, 12], conn);
+            diesel::sql_query("ANALYZE user_copies")
+                .execute(conn)
+                .unwrap();
+
+            // the histogram no longer covers the table
+            diesel::insert_into(user_copies)
+                .values(NewUserCopy {
+                    id: 20,
+                    name: "Copy 20".to_string(),
+                })
+                .execute(conn)
+                .unwrap();
+
+            let mut loaded_ids = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(5).estimated_bounds(),
+                |chunk: Vec<UserCopy>| loaded_ids.extend(chunk.into_iter().map(|copy| copy.id))
+            );
+
+            assert_eq!(loaded_ids, vec![-4, 2, 7, 9, 12, 20]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(21) });
+
+            let mut call_count = 0;
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().estimated_bounds_without_fallback(),
+                |_chunk: Vec<UserCopy>| {
+                    call_count += 1;
+                }
+            );
+
+            assert_eq!(call_count, 0);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+        });
+    }
+
+    #[test]
+    fn falls_back_to_min_and_max_without_statistics() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            insert_user_copies(&[3, 5, 8], conn);
+
+            let mut loaded_ids = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().estimated_bounds(),
+                |chunk: Vec<UserCopy>| loaded_ids.extend(chunk.into_iter().map(|copy| copy.id))
+            );
+
+            assert_eq!(loaded_ids, vec![3, 5, 8]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(9) });
+
+            let mut call_count = 0;
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().estimated_bounds_without_fallback(),
+                |_chunk: Vec<UserCopy>| {
+                    call_count += 1;
+                }
+            );
+
+            assert_eq!(call_count, 0);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+        });
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use clap::Parser;
+    use diesel::prelude::*;
+    use diesel_streamer::cli::{self, Args};
+    use diesel_streamer::StreamEnd;
+
+    use crate::factory::{self, NewUserCopy};
+    use crate::test_runner;
+
+    fn insert_user_copies(conn: &mut PgConnection) {
+        use factory::user_copies::dsl::user_copies;
+
+        factory::create_user_copies_table(conn);
+        diesel::insert_into(user_copies)
+            .values(
+                (1..=5)
+                    .map(|copy_id| NewUserCopy {
+                        id: copy_id,
+                        name: format!("Copy, {copy_id}"),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn args(arguments: &[&str]) -> Args {
+        let mut command_line = vec!["diesel-streamer", "--database-url", "postgres://unused"];
+        command_line.extend_from_slice(arguments);
+
+        Args::parse_from(command_line)
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("diesel-streamer-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn streams_csv_with_progress() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(conn);
+
+            let (mut output, mut progress) = (vec![], vec![]);
+            let stream_end = cli::stream(
+                conn,
+                &args(&["user_copies", "--chunk-size", "2"]),
+                None,
+                &mut output,
+                &mut progress,
+            )
+            .unwrap();
+
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(6) });
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "id,name\n1,\"Copy, 1\"\n2,\"Copy, 2\"\n3,\"Copy, 3\"\n4,\"Copy, 4\"\n5,\"Copy, 5\"\n"
+            );
+
+            let progress = String::from_utf8(progress).unwrap();
+            assert_eq!(progress.lines().count(), 3);
+            assert!(progress
+                .lines()
+                .last()
+                .unwrap()
+                .starts_with("5 rows in 3 chunks, cursor at 6 of 5 (100.0%)"));
+        });
+    }
+
+    #[test]
+    fn streams_json_lines_between_bounds() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(conn);
+
+            let mut output = vec![];
+            cli::stream(
+                conn,
+                &args(&[
+                    "user_copies",
+                    "--format",
+                    "json-lines",
+                    "--from",
+                    "2",
+                    "--to",
+                    "3",
+                    "--quiet",
+                ]),
+                None,
+                &mut output,
+                std::io::sink(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "{\"id\":2,\"name\":\"Copy, 2\"}\n{\"id\":3,\"name\":\"Copy, 3\"}\n"
+            );
+        });
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(conn);
+
+            let checkpoint = checkpoint_path("cli-checkpoint");
+            fs::write(&checkpoint, "4").unwrap();
+
+            let mut output = vec![];
+            let args = args(&[
+                "user_copies",
+                "--checkpoint",
+                checkpoint.to_str().unwrap(),
+                "--quiet",
+            ]);
+
+            let checkpointed_cursor = args.checkpointed_cursor().unwrap();
+            assert_eq!(checkpointed_cursor, Some(4));
+
+            cli::stream(
+                conn,
+                &args,
+                checkpointed_cursor,
+                &mut output,
+                std::io::sink(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "4,\"Copy, 4\"\n5,\"Copy, 5\"\n"
+            );
+            assert_eq!(fs::read_to_string(&checkpoint).unwrap(), "6");
+
+            fs::remove_file(checkpoint).unwrap();
+        });
+    }
+
+    #[test]
+    fn fails_on_missing_table() {
+        test_runner::run_test(|conn| {
+            let error = cli::stream(
+                conn,
+                &args(&["missing"]),
+                None,
+                std::io::sink(),
+                std::io::sink(),
+            )
+            .unwrap_err();
+
+            assert_eq!(error.to_string(), "table missing does not exist");
+        });
+    }
+}
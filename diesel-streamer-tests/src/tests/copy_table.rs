@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel::upsert::excluded;
+    use diesel_streamer::{BatchEnd, StreamEnd, StreamOptions};
+
+    use crate::factory::{self, NewUserCopy, User, UserCopy};
+    use crate::{db, test_runner};
+
+    fn copy_of(user: &User) -> UserCopy {
+        UserCopy {
+            id: user.id,
+            name: user.name.to_uppercase(),
+        }
+    }
+
+    #[test]
+    fn copies_every_row_through_the_mapping() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::user_copies;
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(5, conn);
+            factory::create_user_copies_table(conn);
+
+            let all_users = factory::get_users(conn);
+            let last_user = all_users.last().unwrap();
+
+            let batch_end = diesel_streamer::copy_table!(
+                users,
+                id,
+                conn,
+                into = user_copies,
+                options = StreamOptions::new().chunk_size(2),
+                |user: User| NewUserCopy {
+                    id: user.id,
+                    name: user.name.to_uppercase(),
+                }
+            )
+            .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 5,
+                    stream_end: StreamEnd::Completed {
+                        cursor: Some(last_user.id + 1)
+                    }
+                }
+            );
+            assert_eq!(
+                factory::get_user_copies(conn),
+                all_users.iter().map(copy_of).collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn splits_chunks_past_the_bind_parameter_limit() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::user_copies;
+            use factory::users::dsl::{id, users};
+
+            // 40000 rows of two columns bind 80000 parameters in one chunk
+            factory::insert_users(40000, conn);
+            factory::create_user_copies_table(conn);
+
+            let batch_end =
+                diesel_streamer::copy_table!(users, id, conn, into = user_copies, |user: User| {
+                    NewUserCopy {
+                        id: user.id,
+                        name: user.name.to_uppercase(),
+                    }
+                })
+                .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 40000);
+            assert_eq!(user_copies.count().get_result::<i64>(conn).unwrap(), 40000);
+        });
+    }
+
+    #[test]
+    fn skips_or_updates_conflicting_rows() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id as copy_id, name as copy_name, user_copies};
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+            factory::create_user_copies_table(conn);
+
+            let all_users = factory::get_users(conn);
+
+            diesel::insert_into(user_copies)
+                .values(NewUserCopy {
+                    id: all_users[0].id,
+                    name: "Stale".to_string(),
+                })
+                .execute(conn)
+                .unwrap();
+
+            let batch_end = diesel_streamer::copy_table!(
+                users,
+                id,
+                conn,
+                into = user_copies,
+                on_conflict = copy_id,
+                |user: User| NewUserCopy {
+                    id: user.id,
+                    name: user.name.to_uppercase(),
+                }
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 2);
+            assert_eq!(factory::get_user_copies(conn)[0].name, "Stale");
+
+            let batch_end = diesel_streamer::copy_table!(
+                users,
+                id,
+                conn,
+                into = user_copies,
+                on_conflict = copy_id,
+                do_update = copy_name.eq(excluded(copy_name)),
+                |user: User| NewUserCopy {
+                    id: user.id,
+                    name: user.name.to_uppercase(),
+                }
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 3);
+            assert_eq!(
+                factory::get_user_copies(conn),
+                all_users.iter().map(copy_of).collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn ends_with_the_chunk_that_failed_to_insert() {
+        test_runner::run_test(|conn| {
+            use diesel::result::{DatabaseErrorKind, Error};
+            use factory::user_copies::dsl::user_copies;
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+            factory::create_user_copies_table(conn);
+
+            let all_users = factory::get_users(conn);
+
+            diesel::insert_into(user_copies)
+                .values(NewUserCopy {
+                    id: all_users[2].id,
+                    name: "Stale".to_string(),
+                })
+                .execute(conn)
+                .unwrap();
+
+            let batch_error = diesel_streamer::copy_table!(
+                users,
+                id,
+                conn,
+                into = user_copies,
+                options = StreamOptions::new().chunk_size(2).transaction_per_chunk(),
+                |user: User| NewUserCopy {
+                    id: user.id,
+                    name: user.name.to_uppercase(),
+                }
+            )
+            .unwrap_err();
+
+            assert_eq!(batch_error.chunk, all_users[2].id..=all_users[3].id);
+            assert_eq!(batch_error.affected_rows, 2);
+            assert!(matches!(
+                batch_error.error,
+                Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+            ));
+        });
+    }
+
+    #[test]
+    fn resumes_copying_into_another_connection() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::user_copies;
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let all_users = factory::get_users(conn);
+
+            db::establish_connection().test_transaction(|target_conn| -> Result<(), ()> {
+                factory::create_user_copies_table(target_conn);
+
+                let batch_end = diesel_streamer::copy_table!(
+                    users,
+                    id,
+                    conn,
+                    into = user_copies,
+                    target_conn = target_conn,
+                    options = StreamOptions::new().to(all_users[1].id),
+                    |user: User| NewUserCopy {
+                        id: user.id,
+                        name: user.name.to_uppercase(),
+                    }
+                )
+                .unwrap();
+
+                assert_eq!(batch_end.affected_rows, 2);
+
+                let batch_end = diesel_streamer::copy_table!(
+                    users,
+                    id,
+                    conn,
+                    into = user_copies,
+                    target_conn = target_conn,
+                    options = StreamOptions::new().from(batch_end.stream_end.cursor()),
+                    |user: User| NewUserCopy {
+                        id: user.id,
+                        name: user.name.to_uppercase(),
+                    }
+                )
+                .unwrap();
+
+                assert_eq!(batch_end.affected_rows, 2);
+                assert_eq!(
+                    factory::get_user_copies(target_conn),
+                    all_users.iter().map(copy_of).collect::<Vec<_>>()
+                );
+
+                Ok(())
+            });
+        });
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel_streamer::{CsvSink, CsvSinkBuilder, QuoteStyle, StreamOptions};
+    use serde::Serialize;
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[derive(Debug, Queryable, Serialize)]
+    struct UserRow {
+        id: i32,
+        name: String,
+    }
+
+    #[test]
+    fn writes_streamed_chunks_with_a_header_row() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut sink = CsvSink::new(vec![]);
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |user_rows: Vec<UserRow>| sink.write_chunk(&user_rows)
+            );
+
+            assert!(!stream_end.is_stopped());
+
+            let expected_csv = format!(
+                "id,name\n{},UserName 1\n{},UserName 2\n{},UserName 3\n",
+                all_users[0].id, all_users[1].id, all_users[2].id
+            );
+
+            assert_eq!(
+                String::from_utf8(sink.into_inner().unwrap()).unwrap(),
+                expected_csv
+            );
+        });
+    }
+
+    #[test]
+    fn writes_with_the_configured_delimiter_and_quoting() {
+        let mut sink = CsvSinkBuilder::new()
+            .delimiter(b'\t')
+            .quote(b'\'')
+            .quote_style(QuoteStyle::NonNumeric)
+            .build(vec![]);
+
+        sink.write_chunk(&[UserRow {
+            id: 1,
+            name: "Ada Lovelace".to_string(),
+        }])
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(sink.into_inner().unwrap()).unwrap(),
+            "'id'\t'name'\n1\t'Ada Lovelace'\n"
+        );
+    }
+}
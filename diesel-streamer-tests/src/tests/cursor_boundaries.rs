@@ -0,0 +1,306 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+    use std::time::Duration;
+
+    use diesel::prelude::*;
+    use diesel_streamer::{BatchEnd, DynamicRow, LeaseCoordinator, StreamEnd, StreamOptions};
+    use serde_json::json;
+
+    use crate::factory::{self, NewUserCopy, UserCopy};
+    use crate::test_runner;
+
+    fn insert_user_copies(ids: impl Iterator<Item = i32>, conn: &mut PgConnection) {
+        use factory::user_copies::dsl::user_copies;
+
+        factory::create_user_copies_table(conn);
+        diesel::insert_into(user_copies)
+            .values(
+                ids.map(|copy_id| NewUserCopy {
+                    id: copy_id,
+                    name: format!("Copy {copy_id}"),
+                })
+                .collect::<Vec<_>>(),
+            )
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn stream_ids(
+        conn: &mut PgConnection,
+        options: StreamOptions<i32>,
+    ) -> (Vec<Vec<i32>>, StreamEnd<i32>) {
+        use factory::user_copies::dsl::{id, user_copies};
+
+        let mut chunks = vec![];
+
+        let stream_end = diesel_streamer::stream_serial_table!(
+            user_copies,
+            id,
+            conn,
+            options = options,
+            |copies: Vec<UserCopy>| {
+                chunks.push(copies.into_iter().map(|copy| copy.id).collect());
+            }
+        );
+
+        (chunks, stream_end)
+    }
+
+    #[test]
+    fn streams_up_to_the_cursor_types_maximum() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(i32::MAX - 4..=i32::MAX, conn);
+
+            let (chunks, stream_end) = stream_ids(conn, StreamOptions::new().chunk_size(2));
+
+            assert_eq!(
+                chunks,
+                vec![
+                    vec![i32::MAX - 4, i32::MAX - 3],
+                    vec![i32::MAX - 2, i32::MAX - 1],
+                    vec![i32::MAX],
+                ]
+            );
+            // No cursor follows the maximum
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+
+            let (chunks, stream_end) = stream_ids(
+                conn,
+                StreamOptions::new()
+                    .chunk_size(i32::MAX)
+                    .from(i32::MAX - 1)
+                    .moving_upper_bound(),
+            );
+
+            assert_eq!(chunks, vec![vec![i32::MAX - 1, i32::MAX]]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+        });
+    }
+
+    #[test]
+    fn resumes_at_the_cursor_types_maximum() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(i32::MAX - 4..=i32::MAX, conn);
+
+            let (chunks, stream_end) = stream_ids(conn, StreamOptions::new().to(i32::MAX - 1));
+
+            assert_eq!(chunks, vec![(i32::MAX - 4..i32::MAX).collect::<Vec<_>>()]);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(i32::MAX)
+                }
+            );
+
+            // resuming from there only streams the row at the maximum
+            let (chunks, stream_end) =
+                stream_ids(conn, StreamOptions::new().from(stream_end.cursor()));
+
+            assert_eq!(chunks, vec![vec![i32::MAX]]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+        });
+    }
+
+    #[test]
+    fn streams_nothing_from_partitions_ending_at_the_minimum() {
+        test_runner::run_test(|conn| {
+            insert_user_copies([i32::MIN, i32::MIN + 1].into_iter(), conn);
+
+            let (chunks, stream_end) = stream_ids(
+                conn,
+                StreamOptions::new().partitions(std::iter::once(i32::MIN..i32::MIN).collect()),
+            );
+
+            assert_eq!(chunks, Vec::<Vec<i32>>::new());
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+
+            let (chunks, stream_end) = stream_ids(
+                conn,
+                StreamOptions::new().partitions(std::iter::once(i32::MIN..i32::MIN + 1).collect()),
+            );
+
+            assert_eq!(chunks, vec![vec![i32::MIN]]);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(i32::MIN + 1)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn streams_a_table_whose_only_id_is_zero() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(0..=0, conn);
+
+            let (chunks, stream_end) = stream_ids(conn, StreamOptions::new());
+
+            assert_eq!(chunks, vec![vec![0]]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(1) });
+        });
+    }
+
+    #[test]
+    fn streams_negative_ids() {
+        test_runner::run_test(|conn| {
+            insert_user_copies(-5..=-1, conn);
+
+            let (chunks, stream_end) = stream_ids(conn, StreamOptions::new().chunk_size(3));
+
+            assert_eq!(chunks, vec![vec![-5, -4, -3], vec![-2, -1]]);
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(0) });
+
+            let (chunks, stream_end) = stream_ids(conn, StreamOptions::new().from(1));
+
+            assert_eq!(chunks, Vec::<Vec<i32>>::new());
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(1) });
+        });
+    }
+
+    #[test]
+    fn plans_partitions_and_leases_up_to_the_maximum() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            insert_user_copies(i32::MAX - 9..=i32::MAX, conn);
+
+            let partitions: Vec<Range<i32>> =
+                diesel_streamer::plan_partitions!(user_copies, id, conn, partitions = 2);
+
+            assert_eq!(
+                partitions,
+                vec![i32::MAX - 9..i32::MAX - 4, i32::MAX - 4..i32::MAX]
+            );
+
+            let (chunks, _) = stream_ids(conn, StreamOptions::new().partitions(partitions.clone()));
+
+            // ranges exclude their end, so the maximum is left out
+            assert_eq!(
+                chunks,
+                vec![
+                    (i32::MAX - 9..=i32::MAX - 5).collect::<Vec<_>>(),
+                    (i32::MAX - 4..i32::MAX).collect::<Vec<_>>(),
+                ]
+            );
+
+            let (chunks, _) = stream_ids(
+                conn,
+                StreamOptions::new()
+                    .partitions(partitions.clone())
+                    .to(i32::MAX),
+            );
+
+            assert_eq!(
+                chunks.concat(),
+                (i32::MAX - 9..=i32::MAX).collect::<Vec<_>>()
+            );
+
+            let coordinator = LeaseCoordinator::new("copy_user_copies", "worker-1")
+                .poll_interval(Duration::from_millis(10));
+            coordinator.create_table_query().execute(conn).unwrap();
+            coordinator.plan_query(&partitions).execute(conn).unwrap();
+
+            let mut leased_ids = vec![];
+
+            let stream_end = diesel_streamer::stream_leased_table!(
+                user_copies,
+                id,
+                conn,
+                coordinator = coordinator,
+                chunk_size = 3,
+                |copies: Vec<UserCopy>| leased_ids.extend(copies.into_iter().map(|copy| copy.id))
+            )
+            .unwrap();
+
+            assert_eq!(leased_ids, (i32::MAX - 9..i32::MAX).collect::<Vec<_>>());
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(i32::MAX)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn dry_runs_and_deletes_up_to_the_maximum() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            insert_user_copies(i32::MAX - 2..=i32::MAX, conn);
+
+            let options = || StreamOptions::new().chunk_size(2);
+            let dry_run =
+                diesel_streamer::dry_run!(user_copies, id, conn, options = options()).unwrap();
+
+            assert_eq!(dry_run.chunks, 2);
+            assert_eq!(dry_run.sample_chunk, Some(i32::MAX - 2..=i32::MAX - 1));
+
+            let batch_end =
+                diesel_streamer::delete_in_batches!(user_copies, id, conn, options = options())
+                    .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 3,
+                    stream_end: StreamEnd::Completed { cursor: None }
+                }
+            );
+            assert_eq!(factory::get_user_copies(conn), vec![]);
+        });
+    }
+
+    #[test]
+    fn streams_smallint_and_bigint_cursors_up_to_their_maximum() {
+        test_runner::run_test(|conn| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE small_events (position SMALLINT PRIMARY KEY)",
+            )
+            .execute(conn)
+            .unwrap();
+            diesel::sql_query("INSERT INTO small_events VALUES (32765), (32766), (32767)")
+                .execute(conn)
+                .unwrap();
+            diesel::sql_query("CREATE TEMPORARY TABLE big_events (position BIGINT PRIMARY KEY)")
+                .execute(conn)
+                .unwrap();
+            diesel::sql_query(format!(
+                "INSERT INTO big_events VALUES ({}), ({})",
+                i64::MAX - 1,
+                i64::MAX
+            ))
+            .execute(conn)
+            .unwrap();
+
+            for (table, positions) in [
+                ("small_events", vec![32765, 32766, 32767]),
+                ("big_events", vec![i64::MAX - 1, i64::MAX]),
+            ] {
+                let mut streamed_positions = vec![];
+
+                let stream_end = diesel_streamer::stream_dynamic_table!(
+                    table,
+                    conn,
+                    options = StreamOptions::new().chunk_size(2),
+                    |rows: Vec<DynamicRow>| {
+                        streamed_positions
+                            .extend(rows.into_iter().map(|row| row["position"].clone()));
+                    }
+                )
+                .unwrap();
+
+                assert_eq!(
+                    streamed_positions,
+                    positions
+                        .iter()
+                        .map(|&position| json!(position))
+                        .collect::<Vec<_>>()
+                );
+                assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+            }
+        });
+    }
+}
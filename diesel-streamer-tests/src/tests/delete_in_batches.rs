@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+    use std::time::{Duration, Instant};
+
+    use diesel_streamer::{BatchEnd, StreamEnd, StreamOptions};
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[test]
+    fn deletes_only_filtered_rows_in_chunks() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(5, conn);
+
+            let last_user = factory::get_users(conn).pop().unwrap();
+
+            let batch_end = diesel_streamer::delete_in_batches!(
+                users.filter(name.ne("UserName 2")),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2)
+            )
+            .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 4,
+                    stream_end: StreamEnd::Completed {
+                        cursor: Some(last_user.id + 1)
+                    }
+                }
+            );
+
+            let remaining_users = factory::get_users(conn);
+
+            assert_eq!(remaining_users.len(), 1);
+            assert_eq!(remaining_users[0].name, "UserName 2");
+        });
+    }
+
+    #[test]
+    fn passes_deleted_rows_to_the_processor() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut archived_users = vec![];
+
+            let batch_end = diesel_streamer::delete_in_batches!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2).transaction_per_chunk(),
+                |deleted_users: Vec<User>| archived_users.extend(deleted_users)
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 3);
+            assert_eq!(archived_users, all_users);
+            assert!(factory::get_users(conn).is_empty());
+        });
+    }
+
+    #[test]
+    fn stops_deleting_when_processor_breaks() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut archived_chunks = 0;
+
+            let batch_end = diesel_streamer::delete_in_batches!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |_deleted_users: Vec<User>| {
+                    archived_chunks += 1;
+
+                    if archived_chunks == 2 {
+                        ControlFlow::Break("archive is full")
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                }
+            )
+            .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 2,
+                    stream_end: StreamEnd::Stopped {
+                        cursor: Some(all_users[2].id),
+                        reason: "archive is full"
+                    }
+                }
+            );
+            assert_eq!(factory::get_users(conn), all_users[2..]);
+        });
+    }
+
+    #[test]
+    fn keeps_the_rows_the_processor_failed_on() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let batch_end = diesel_streamer::delete_in_batches!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |deleted_users: Vec<User>| if deleted_users.len() == 2 {
+                    Err("archive is unreachable")
+                } else {
+                    Ok(())
+                }
+            )
+            .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 0,
+                    stream_end: StreamEnd::Stopped {
+                        cursor: Some(all_users[0].id),
+                        reason: "archive is unreachable"
+                    }
+                }
+            );
+            assert_eq!(factory::get_users(conn), all_users);
+        });
+    }
+
+    #[test]
+    fn pauses_between_chunks() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let started_at = Instant::now();
+
+            let batch_end = diesel_streamer::delete_in_batches!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new()
+                    .chunk_size(1)
+                    .pause(Duration::from_millis(50))
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 3);
+            assert!(started_at.elapsed() >= Duration::from_millis(100));
+        });
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel_streamer::StreamOptions;
+
+    use crate::factory::{self, NewUserCopy};
+    use crate::test_runner;
+
+    #[test]
+    fn reports_bounds_chunks_and_plan() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            let all_users = factory::get_users(conn);
+            let (first_id, last_id) = (all_users[0].id, all_users[9].id);
+
+            let dry_run = diesel_streamer::dry_run!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(3)
+            )
+            .unwrap();
+
+            assert_eq!(dry_run.from, Some(first_id));
+            assert_eq!(dry_run.to, Some(last_id));
+            assert_eq!(dry_run.chunks, 4);
+            assert_eq!(dry_run.sample_chunk, Some(first_id..=first_id + 2));
+            assert!(!dry_run.analyzed);
+            assert!(dry_run.plan[0].contains("users"));
+            assert!(dry_run.to_string().contains("chunks: 4"));
+        });
+    }
+
+    #[test]
+    fn warns_about_sequential_scans() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            diesel::sql_query("SET LOCAL enable_indexscan = off")
+                .execute(conn)
+                .unwrap();
+            diesel::sql_query("SET LOCAL enable_bitmapscan = off")
+                .execute(conn)
+                .unwrap();
+
+            let dry_run = diesel_streamer::dry_run!(users, id, conn).unwrap();
+
+            assert!(dry_run.sequential_scan);
+            assert_eq!(
+                dry_run.warnings,
+                vec!["the chunk query scans users sequentially instead of through an index on id"]
+            );
+
+            diesel::sql_query("SET LOCAL enable_indexscan = on")
+                .execute(conn)
+                .unwrap();
+            diesel::sql_query("SET LOCAL enable_seqscan = off")
+                .execute(conn)
+                .unwrap();
+
+            let dry_run =
+                diesel_streamer::dry_run!(users, id, conn, select = (id, factory::users::name))
+                    .unwrap();
+
+            assert!(!dry_run.sequential_scan);
+            assert!(dry_run.warnings.is_empty());
+        });
+    }
+
+    #[test]
+    fn analyzes_one_sample_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            let dry_run = diesel_streamer::dry_run!(users, id, conn, analyze = true).unwrap();
+
+            assert_eq!(dry_run.from, None);
+            assert_eq!(dry_run.chunks, 0);
+            assert!(dry_run.plan.is_empty());
+
+            factory::insert_users(3, conn);
+
+            let dry_run = diesel_streamer::dry_run!(users, id, conn, analyze = true).unwrap();
+
+            assert!(dry_run.analyzed);
+            assert_eq!(dry_run.chunks, 1);
+            assert!(dry_run.plan.iter().any(|line| line.contains("actual time")));
+        });
+    }
+
+    #[test]
+    fn returns_the_error_of_the_explain() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let error = diesel_streamer::dry_run!(users, id, conn, select = id / 0, analyze = true)
+                .unwrap_err();
+
+            assert!(error.to_string().contains("division by zero"));
+        });
+    }
+
+    #[test]
+    fn counts_chunks_along_partitions() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            factory::create_user_copies_table(conn);
+            diesel::insert_into(user_copies)
+                .values(
+                    (1..=10)
+                        .map(|copy_id| NewUserCopy {
+                            id: copy_id,
+                            name: format!("Copy {copy_id}"),
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)
+                .unwrap();
+
+            let dry_run = diesel_streamer::dry_run!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new()
+                    .chunk_size(2)
+                    .partitions(vec![1..3, 5..8])
+            )
+            .unwrap();
+
+            assert_eq!((dry_run.from, dry_run.to), (Some(1), Some(7)));
+            assert_eq!(dry_run.chunks, 3);
+            assert_eq!(dry_run.sample_chunk, Some(1..=2));
+        });
+    }
+}
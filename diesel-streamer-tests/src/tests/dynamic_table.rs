@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel_streamer::{DynamicRow, DynamicTableError, StreamEnd, StreamOptions};
+    use serde_json::json;
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[test]
+    fn streams_rows_by_primary_key() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+            let mut streamed_rows = vec![];
+
+            let stream_end =
+                diesel_streamer::stream_dynamic_table!("users", conn, |rows: Vec<DynamicRow>| {
+                    streamed_rows.extend(rows);
+                })
+                .unwrap();
+
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(i64::from(all_users.last().unwrap().id) + 1)
+                }
+            );
+            assert_eq!(streamed_rows.len(), 3);
+
+            for (row, user) in streamed_rows.iter().zip(&all_users) {
+                assert_eq!(row["id"], json!(user.id));
+                assert_eq!(row["name"], json!(user.name));
+                assert!(row["created_at"].is_string());
+                assert_eq!(
+                    row.keys().collect::<Vec<_>>(),
+                    ["created_at", "id", "name", "updated_at"]
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn streams_by_the_named_cursor_within_options() {
+        test_runner::run_test(|conn| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE events (position SMALLINT NOT NULL, payload JSONB, amount NUMERIC)",
+            )
+            .execute(conn)
+            .unwrap();
+            diesel::sql_query(
+                "INSERT INTO events SELECT n, jsonb_build_object('n', n), n / 4.0 FROM generate_series(1, 10) AS n",
+            )
+            .execute(conn)
+            .unwrap();
+            diesel::sql_query("UPDATE events SET payload = NULL WHERE position = 3")
+                .execute(conn)
+                .unwrap();
+
+            let mut chunks = vec![];
+
+            let stream_end = diesel_streamer::stream_dynamic_table!(
+                String::from("pg_temp.events"),
+                conn,
+                cursor = "position",
+                options = StreamOptions::new().from(2).to(5).chunk_size(2),
+                |rows: Vec<DynamicRow>| {
+                    chunks.push(rows);
+                }
+            )
+            .unwrap();
+
+            assert_eq!(stream_end, StreamEnd::Completed { cursor: Some(6) });
+            assert_eq!(
+                chunks
+                    .iter()
+                    .map(|rows| rows.iter().map(|row| row["position"].clone()).collect())
+                    .collect::<Vec<Vec<_>>>(),
+                vec![vec![json!(2), json!(3)], vec![json!(4), json!(5)]]
+            );
+
+            let row = &chunks[0][0];
+            assert_eq!(row["payload"], json!({ "n": 2 }));
+            assert_eq!(row["amount"], json!("0.50000000000000000000"));
+            assert_eq!(chunks[0][1]["payload"], json!(null));
+        });
+    }
+
+    #[test]
+    fn fails_without_a_cursor_to_stream_by() {
+        test_runner::run_test(|conn| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE memberships (user_id INTEGER, group_id INTEGER, role TEXT, PRIMARY KEY (user_id, group_id))",
+            )
+            .execute(conn)
+            .unwrap();
+            diesel::sql_query("CREATE TEMPORARY TABLE logs (line TEXT)")
+                .execute(conn)
+                .unwrap();
+
+            let mut call_count = 0;
+            let mut stream = |table: &str, cursor: Option<&str>| match cursor {
+                Some(cursor) => diesel_streamer::stream_dynamic_table!(
+                    table,
+                    conn,
+                    cursor = cursor,
+                    |_rows: Vec<DynamicRow>| {
+                        call_count += 1;
+                    }
+                ),
+                None => {
+                    diesel_streamer::stream_dynamic_table!(table, conn, |_rows: Vec<DynamicRow>| {
+                        call_count += 1;
+                    })
+                }
+            };
+
+            assert_eq!(
+                stream("missing", None),
+                Err(DynamicTableError::NotFound("missing".to_owned()))
+            );
+            assert_eq!(
+                stream("memberships", None),
+                Err(DynamicTableError::CompositePrimaryKey(
+                    "memberships".to_owned()
+                ))
+            );
+            assert_eq!(
+                stream("logs", None),
+                Err(DynamicTableError::NoPrimaryKey("logs".to_owned()))
+            );
+            assert_eq!(
+                stream("memberships", Some("level")),
+                Err(DynamicTableError::UnknownColumn {
+                    table: "memberships".to_owned(),
+                    column: "level".to_owned(),
+                })
+            );
+            assert_eq!(
+                stream("memberships", Some("role")),
+                Err(DynamicTableError::UnsupportedCursor {
+                    column: "role".to_owned(),
+                    type_name: "text".to_owned(),
+                })
+            );
+            assert!(stream("memberships", Some("user_id")).is_ok());
+            assert!(matches!(
+                stream("too.many.dotted.names", None),
+                Err(DynamicTableError::Query(_))
+            ));
+
+            assert_eq!(call_count, 0);
+        });
+    }
+
+    #[test]
+    fn streams_nothing_out_of_the_cursor_type() {
+        test_runner::run_test(|conn| {
+            diesel::sql_query("CREATE TEMPORARY TABLE events (position SMALLINT PRIMARY KEY)")
+                .execute(conn)
+                .unwrap();
+            diesel::sql_query("INSERT INTO events VALUES (-32768), (1), (32767)")
+                .execute(conn)
+                .unwrap();
+
+            let mut streamed_rows = vec![];
+
+            for options in [
+                StreamOptions::new().from(40000),
+                StreamOptions::new().to(-40000),
+            ] {
+                let stream_end = diesel_streamer::stream_dynamic_table!(
+                    "events",
+                    conn,
+                    options = options,
+                    |rows: Vec<DynamicRow>| {
+                        streamed_rows.extend(rows);
+                    }
+                )
+                .unwrap();
+
+                assert_eq!(stream_end, StreamEnd::Completed { cursor: None });
+            }
+
+            assert!(streamed_rows.is_empty());
+
+            // bounds past the type's values still cover every row
+            diesel_streamer::stream_dynamic_table!(
+                "events",
+                conn,
+                options = StreamOptions::new().from(-40000).to(40000),
+                |rows: Vec<DynamicRow>| {
+                    streamed_rows.extend(rows);
+                }
+            )
+            .unwrap();
+
+            assert_eq!(streamed_rows.len(), 3);
+        });
+    }
+}
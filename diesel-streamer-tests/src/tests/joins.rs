@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use diesel::pg::Pg;
+    use diesel::prelude::*;
+    use diesel_streamer::{StreamEnd, StreamOptions};
+
+    use crate::factory::{self, posts, users, Post, User};
+    use crate::test_runner;
+
+    fn titled_posts<'a>() -> posts::BoxedQuery<'a, Pg> {
+        posts::table
+            .filter(posts::title.like("Post by %"))
+            .into_boxed()
+    }
+
+    #[test]
+    fn streams_inner_joins_by_the_driving_table() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(3, conn);
+            factory::create_posts_table(conn);
+
+            let all_posts = factory::insert_posts(conn);
+            let all_users = factory::get_users(conn);
+
+            let mut loaded_rows = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                posts::table.inner_join(users::table),
+                posts::id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |rows: Vec<(Post, User)>| loaded_rows.extend(rows)
+            );
+
+            loaded_rows.sort_by_key(|(post, _user)| post.id);
+
+            assert_eq!(
+                loaded_rows,
+                vec![
+                    (all_posts[0].clone(), all_users[0].clone()),
+                    (all_posts[2].clone(), all_users[1].clone()),
+                    (all_posts[3].clone(), all_users[2].clone()),
+                ]
+            );
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(all_posts[3].id + 1)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn streams_left_joins_by_the_driving_table() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(2, conn);
+            factory::create_posts_table(conn);
+
+            let all_posts = factory::insert_posts(conn);
+
+            let mut loaded_rows = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                posts::table
+                    .left_join(users::table)
+                    .filter(posts::title.ne("Post by UserName 2")),
+                posts::id,
+                conn,
+                select = (posts::title, users::name.nullable()),
+                |rows: Vec<(String, Option<String>)>| loaded_rows.extend(rows)
+            );
+
+            loaded_rows.sort();
+
+            assert_eq!(
+                loaded_rows,
+                vec![
+                    (all_posts[1].title.clone(), None),
+                    (all_posts[0].title.clone(), Some("UserName 1".to_string())),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn streams_boxed_queries() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(3, conn);
+            factory::create_posts_table(conn);
+
+            let all_posts = factory::insert_posts(conn);
+
+            let mut loaded_posts = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                titled_posts(),
+                posts::id,
+                conn,
+                options = StreamOptions::new().chunk_size(1),
+                |rows: Vec<Post>| loaded_posts.extend(rows)
+            );
+
+            assert_eq!(
+                loaded_posts,
+                vec![
+                    all_posts[0].clone(),
+                    all_posts[2].clone(),
+                    all_posts[3].clone()
+                ]
+            );
+
+            let mut loaded_titles = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                posts::table.inner_join(users::table).into_boxed(),
+                posts::id,
+                conn,
+                select = posts::title,
+                |rows: Vec<String>| loaded_titles.extend(rows)
+            );
+
+            loaded_titles.sort();
+
+            assert_eq!(
+                loaded_titles,
+                vec![
+                    all_posts[0].title.clone(),
+                    all_posts[2].title.clone(),
+                    all_posts[3].title.clone()
+                ]
+            );
+        });
+    }
+}
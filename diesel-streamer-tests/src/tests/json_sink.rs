@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use diesel::prelude::*;
+    use diesel_streamer::{JsonLinesSink, StreamOptions};
+    use serde::Serialize;
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[derive(Debug, Queryable, Serialize)]
+    struct UserRow {
+        id: i32,
+        name: String,
+    }
+
+    /// Records what was written by the time of each flush.
+    #[derive(Default)]
+    struct FlushRecorder {
+        written: Vec<u8>,
+        flushed: Vec<String>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed
+                .push(String::from_utf8(self.written.clone()).unwrap());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_one_json_object_per_row_and_flushes_after_each_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut sink = JsonLinesSink::new(FlushRecorder::default());
+
+            diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |user_rows: Vec<UserRow>| sink.write_chunk(&user_rows)
+            );
+
+            let lines: Vec<String> = all_users
+                .iter()
+                .map(|user| {
+                    serde_json::json!({ "id": user.id, "name": user.name }).to_string() + "\n"
+                })
+                .collect();
+
+            assert_eq!(
+                sink.into_inner().flushed,
+                [lines[..2].concat(), lines.concat()]
+            );
+        });
+    }
+}
@@ -0,0 +1,277 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use diesel::prelude::*;
+    use diesel_streamer::{
+        LeaseCoordinator, LeaseRow, Progress, RemainingUnits, StreamEnd, StreamOptions,
+    };
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    fn plan_units(coordinator: &LeaseCoordinator, conn: &mut PgConnection) -> Vec<Range<i32>> {
+        let ids = factory::get_users(conn)
+            .into_iter()
+            .map(|user| user.id)
+            .collect::<Vec<_>>();
+        let units = ids
+            .chunks(4)
+            .map(|chunk| chunk[0]..chunk[chunk.len() - 1] + 1)
+            .collect::<Vec<_>>();
+
+        coordinator.create_table_query().execute(conn).unwrap();
+        coordinator.plan_query(&units).execute(conn).unwrap();
+
+        units
+    }
+
+    fn remaining_units(coordinator: &LeaseCoordinator, conn: &mut PgConnection) -> i64 {
+        coordinator
+            .remaining_query()
+            .get_result::<RemainingUnits>(conn)
+            .unwrap()
+            .remaining
+    }
+
+    #[test]
+    fn streams_every_unit_once() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            // names are bound as parameters, quotes included
+            let coordinator = LeaseCoordinator::new("backfill_'users'", "worker-1");
+            let units = plan_units(&coordinator, conn);
+            // planning again, as every worker does on start, is a no-op
+            plan_units(&coordinator, conn);
+
+            let mut loaded_users = vec![];
+
+            let stream_end = diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = coordinator.clone(),
+                chunk_size = 3,
+                |chunk: Vec<User>| loaded_users.extend(chunk)
+            )
+            .unwrap();
+
+            assert_eq!(loaded_users, factory::get_users(conn));
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(units.last().unwrap().end)
+                }
+            );
+            assert_eq!(remaining_units(&coordinator, conn), 0);
+        });
+    }
+
+    #[test]
+    fn skips_leased_units_and_reclaims_expired_ones() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(10, conn);
+
+            let first_worker = LeaseCoordinator::new("backfill_users", "worker-1");
+            let second_worker = LeaseCoordinator::new("backfill_users", "worker-2");
+            let dying_worker =
+                LeaseCoordinator::new("backfill_users", "worker-3").lease_duration(Duration::ZERO);
+            let units = plan_units(&first_worker, conn);
+
+            let claim = |coordinator: &LeaseCoordinator, conn: &mut PgConnection| {
+                coordinator
+                    .claim_query()
+                    .get_result::<LeaseRow>(conn)
+                    .optional()
+                    .unwrap()
+                    .and_then(|row| row.lease::<i32>())
+            };
+
+            let first_lease = claim(&first_worker, conn).unwrap();
+            let second_lease = claim(&second_worker, conn).unwrap();
+            let dying_lease = claim(&dying_worker, conn).unwrap();
+
+            assert_eq!(first_lease.from..first_lease.to + 1, units[0]);
+            assert_eq!(second_lease.from..second_lease.to + 1, units[1]);
+            assert_eq!(dying_lease.from..dying_lease.to + 1, units[2]);
+
+            let heartbeats = first_worker
+                .heartbeat_query(&first_lease)
+                .execute(conn)
+                .unwrap();
+            assert_eq!(heartbeats, 1);
+
+            // only the expired lease is claimed again
+            let reclaimed_lease = claim(&second_worker, conn).unwrap();
+            assert_eq!(reclaimed_lease.from, dying_lease.from);
+            assert_eq!(reclaimed_lease.attempts, 2);
+            assert_eq!(claim(&first_worker, conn), None);
+
+            let completions = dying_worker
+                .complete_query(&dying_lease)
+                .execute(conn)
+                .unwrap();
+            assert_eq!(completions, 0);
+
+            for (coordinator, lease) in [
+                (&first_worker, first_lease),
+                (&second_worker, second_lease),
+                (&second_worker, reclaimed_lease),
+            ] {
+                let completions = coordinator.complete_query(&lease).execute(conn).unwrap();
+                assert_eq!(completions, 1);
+            }
+
+            assert_eq!(remaining_units(&first_worker, conn), 0);
+        });
+    }
+
+    #[test]
+    fn takes_over_the_units_of_stopped_workers() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            let stopped_worker =
+                LeaseCoordinator::new("backfill_users", "worker-1").lease_duration(Duration::ZERO);
+            let units = plan_units(&stopped_worker, conn);
+
+            let stream_end = diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = stopped_worker,
+                chunk_size = 2,
+                |_chunk: Vec<User>| Err("crashed")
+            )
+            .unwrap();
+
+            assert_eq!(
+                stream_end,
+                StreamEnd::Stopped {
+                    cursor: Some(units[0].start),
+                    reason: "crashed"
+                }
+            );
+
+            let mut loaded_users = vec![];
+
+            let stream_end = diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = LeaseCoordinator::new("backfill_users", "worker-2")
+                    .poll_interval(Duration::from_millis(10)),
+                |chunk: Vec<User>| loaded_users.extend(chunk)
+            )
+            .unwrap();
+
+            assert_eq!(loaded_users, factory::get_users(conn));
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(units.last().unwrap().end)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn reports_progress_through_each_unit_until_cancelled() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            let coordinator = LeaseCoordinator::new("backfill_users", "worker-1");
+            let units = plan_units(&coordinator, conn);
+
+            let all_users = factory::get_users(conn);
+            let cancellation = Arc::new(AtomicBool::new(false));
+            let reports = Arc::new(Mutex::new(vec![]));
+            let mut loaded_users = vec![];
+
+            let stream_end = diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = coordinator.clone(),
+                options = StreamOptions::new()
+                    .chunk_size(2)
+                    .cancellation(cancellation.clone())
+                    .on_progress({
+                        let reports = reports.clone();
+
+                        move |progress: &Progress<i32>| {
+                            reports.lock().unwrap().push((
+                                progress.from,
+                                progress.cursor,
+                                progress.chunks,
+                            ));
+                        }
+                    }),
+                |chunk: Vec<User>| {
+                    loaded_users.extend(chunk);
+
+                    if loaded_users.len() == 6 {
+                        cancellation.store(true, Ordering::SeqCst);
+                    }
+                }
+            )
+            .unwrap();
+
+            assert_eq!(loaded_users, all_users[..6]);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Cancelled {
+                    cursor: Some(all_users[6].id)
+                }
+            );
+            assert_eq!(
+                *reports.lock().unwrap(),
+                vec![
+                    (units[0].start, units[0].start + 2, 1),
+                    (units[0].start, units[0].end, 2),
+                    (units[1].start, units[1].start + 2, 1),
+                ]
+            );
+            // the cancelled unit is left for its lease to expire
+            assert_eq!(remaining_units(&coordinator, conn), 2);
+        });
+    }
+
+    #[test]
+    fn ends_with_the_error_of_a_failed_claim() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+            diesel::sql_query("DROP TABLE IF EXISTS diesel_streamer_leases")
+                .execute(conn)
+                .unwrap();
+
+            let mut call_count = 0;
+
+            let error = diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = LeaseCoordinator::new("backfill_users", "worker-1"),
+                |_chunk: Vec<User>| {
+                    call_count += 1;
+                }
+            )
+            .unwrap_err();
+
+            assert_eq!(call_count, 0);
+            assert!(error.to_string().contains("diesel_streamer_leases"));
+        });
+    }
+}
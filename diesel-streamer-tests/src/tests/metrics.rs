@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use diesel::prelude::*;
+    use diesel_streamer::StreamOptions;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::MetricKind;
+
+    use crate::factory::{self, NewUserCopy, User};
+    use crate::test_runner;
+
+    #[test]
+    fn records_metrics_labeled_with_the_stream_name() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+
+            let recorder = DebuggingRecorder::new();
+            let snapshotter = recorder.snapshotter();
+
+            metrics::with_local_recorder(&recorder, || {
+                diesel_streamer::stream_serial_table!(
+                    users,
+                    id,
+                    conn,
+                    options = StreamOptions::new().chunk_size(2).name("users_backfill"),
+                    |_loaded_users: Vec<User>| {}
+                );
+            });
+
+            let metrics = snapshotter.snapshot().into_vec();
+            let metric = |kind: MetricKind, name: &str| {
+                metrics
+                    .iter()
+                    .find(|(key, _, _, _)| key.kind() == kind && key.key().name() == name)
+                    .map(|(key, _, _, value)| {
+                        let labels: Vec<_> = key
+                            .key()
+                            .labels()
+                            .map(|label| (label.key().to_string(), label.value().to_string()))
+                            .collect();
+
+                        assert_eq!(
+                            labels,
+                            vec![("stream".to_string(), "users_backfill".to_string())]
+                        );
+
+                        value
+                    })
+                    .unwrap()
+            };
+
+            assert_eq!(
+                metric(MetricKind::Counter, "diesel_streamer_rows_total"),
+                &DebugValue::Counter(3)
+            );
+            assert_eq!(
+                metric(MetricKind::Counter, "diesel_streamer_chunks_total"),
+                &DebugValue::Counter(2)
+            );
+            assert_eq!(
+                metric(MetricKind::Counter, "diesel_streamer_empty_chunks_total"),
+                &DebugValue::Counter(0)
+            );
+
+            match metric(MetricKind::Histogram, "diesel_streamer_chunk_rows") {
+                DebugValue::Histogram(chunk_sizes) => {
+                    let chunk_sizes: Vec<f64> =
+                        chunk_sizes.iter().map(|size| size.into_inner()).collect();
+
+                    assert_eq!(chunk_sizes, vec![2.0, 1.0]);
+                }
+                value => panic!("unexpected value {value:?}"),
+            }
+
+            for name in [
+                "diesel_streamer_chunk_query_duration_seconds",
+                "diesel_streamer_processor_duration_seconds",
+            ] {
+                match metric(MetricKind::Histogram, name) {
+                    DebugValue::Histogram(durations) => assert_eq!(durations.len(), 2),
+                    value => panic!("unexpected value {value:?}"),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn counts_retries_errors_and_stops() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::user_copies;
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(3, conn);
+            factory::create_user_copies_table(conn);
+
+            let all_users = factory::get_users(conn);
+
+            diesel::insert_into(user_copies)
+                .values(NewUserCopy {
+                    id: all_users[2].id,
+                    name: "Stale".to_string(),
+                })
+                .execute(conn)
+                .unwrap();
+
+            let recorder = DebuggingRecorder::new();
+            let snapshotter = recorder.snapshotter();
+
+            metrics::with_local_recorder(&recorder, || {
+                let batch_result = diesel_streamer::copy_table!(
+                    users,
+                    id,
+                    conn,
+                    into = user_copies,
+                    options = StreamOptions::new()
+                        .chunk_size(2)
+                        .transaction_per_chunk()
+                        .retries(2),
+                    |user: User| NewUserCopy {
+                        id: user.id,
+                        name: user.name,
+                    }
+                );
+                assert!(batch_result.is_err());
+
+                diesel_streamer::stream_serial_table!(users, id, conn, |_loaded_users: Vec<
+                    User,
+                >| Err(
+                    "processor failed"
+                ));
+
+                diesel_streamer::stream_serial_table!(users, id, conn, |_loaded_users: Vec<
+                    User,
+                >| {
+                    ControlFlow::Break("done")
+                });
+            });
+
+            let metrics = snapshotter.snapshot().into_vec();
+            let counter = |name: &str| {
+                metrics
+                    .iter()
+                    .filter(|(key, _, _, _)| key.key().name() == name)
+                    .map(|(_, _, _, value)| match value {
+                        DebugValue::Counter(value) => *value,
+                        value => panic!("unexpected value {value:?}"),
+                    })
+                    .sum::<u64>()
+            };
+
+            // the failing insert is tried thrice, then one processor fails and
+            // another stops once done, which is no error
+            assert_eq!(counter("diesel_streamer_retries_total"), 2);
+            assert_eq!(counter("diesel_streamer_errors_total"), 4);
+            assert_eq!(counter("diesel_streamer_stops_total"), 2);
+        });
+    }
+}
@@ -1 +1,21 @@
+mod bounds;
+mod cli;
+mod copy_table;
+mod csv_sink;
+mod cursor_boundaries;
+mod delete_in_batches;
+mod dry_run;
+mod dynamic_table;
+mod joins;
+mod json_sink;
+mod leases;
+mod metrics;
+mod parquet_sink;
+mod partitions;
+mod progress;
+mod record_batch;
 mod serial_table;
+mod shards;
+mod streamable;
+mod tracing;
+mod update_in_batches;
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    use diesel::prelude::*;
+    use diesel_streamer::{ParquetFile, ParquetSink, RecordBatchEncoder, StreamOptions};
+    use parquet::file::metadata::RowGroupMetaData;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use serde::Serialize;
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[derive(Debug, Queryable, Serialize)]
+    struct UserRow {
+        id: i32,
+        name: String,
+    }
+
+    fn export_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("diesel-streamer-{}-{name}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&directory);
+
+        directory
+    }
+
+    fn row_group_sizes(file: PathBuf) -> Vec<i64> {
+        SerializedFileReader::new(File::open(file).unwrap())
+            .unwrap()
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(RowGroupMetaData::num_rows)
+            .collect()
+    }
+
+    #[test]
+    fn writes_a_row_group_per_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+            let directory = export_directory("row-groups");
+
+            let mut sink =
+                ParquetSink::new(&directory, RecordBatchEncoder::for_columns((id, name)))
+                    .file_prefix("users");
+
+            diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |user_rows: Vec<UserRow>| sink.write_chunk(&user_rows, |user_row| user_row.id)
+            );
+
+            let manifest = sink.finish().unwrap();
+
+            assert_eq!(
+                manifest.files,
+                [ParquetFile {
+                    file_name: "users-00000.parquet".to_string(),
+                    rows: 5,
+                    from: all_users[0].id,
+                    to: all_users[4].id,
+                }]
+            );
+            assert_eq!(
+                row_group_sizes(directory.join("users-00000.parquet")),
+                [2, 2, 1]
+            );
+
+            let manifest_json: serde_json::Value =
+                serde_json::from_reader(File::open(directory.join("users-manifest.json")).unwrap())
+                    .unwrap();
+
+            assert_eq!(manifest_json["files"][0]["rows"], 5);
+
+            fs::remove_dir_all(directory).unwrap();
+        });
+    }
+
+    #[test]
+    fn rolls_over_to_a_new_file_after_max_rows() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+            let directory = export_directory("rollover");
+
+            let mut sink = ParquetSink::new(&directory, RecordBatchEncoder::infer())
+                .chunks_per_row_group(2)
+                .max_rows_per_file(4);
+
+            diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(1),
+                |user_rows: Vec<UserRow>| sink.write_chunk(&user_rows, |user_row| user_row.id)
+            );
+
+            let manifest = sink.finish().unwrap();
+
+            assert_eq!(
+                manifest.files,
+                [
+                    ParquetFile {
+                        file_name: "part-00000.parquet".to_string(),
+                        rows: 4,
+                        from: all_users[0].id,
+                        to: all_users[3].id,
+                    },
+                    ParquetFile {
+                        file_name: "part-00001.parquet".to_string(),
+                        rows: 1,
+                        from: all_users[4].id,
+                        to: all_users[4].id,
+                    }
+                ]
+            );
+            assert_eq!(
+                row_group_sizes(directory.join("part-00000.parquet")),
+                [2, 2]
+            );
+            assert_eq!(row_group_sizes(directory.join("part-00001.parquet")), [1]);
+
+            fs::remove_dir_all(directory).unwrap();
+        });
+    }
+}
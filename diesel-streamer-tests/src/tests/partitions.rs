@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use diesel::prelude::*;
+    use diesel_streamer::{StreamEnd, StreamOptions};
+
+    use crate::factory::{self, NewUserCopy, UserCopy};
+    use crate::test_runner;
+
+    table! {
+        user_copy_views (id) {
+            id -> Int4,
+            name -> Varchar,
+        }
+    }
+
+    /// 30 consecutive ids followed by 10 sparse ones.
+    fn insert_skewed_user_copies(conn: &mut PgConnection) -> Vec<i32> {
+        use factory::user_copies::dsl::user_copies;
+
+        let ids = (1..=30)
+            .chain((1..=10).map(|step| step * 1000))
+            .collect::<Vec<_>>();
+
+        factory::create_user_copies_table(conn);
+
+        diesel::insert_into(user_copies)
+            .values(
+                ids.iter()
+                    .map(|&id| NewUserCopy {
+                        id,
+                        name: format!("Copy {id}"),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)
+            .unwrap();
+
+        ids
+    }
+
+    fn rows_per_partition(ids: &[i32], partitions: &[Range<i32>]) -> Vec<usize> {
+        partitions
+            .iter()
+            .map(|partition| ids.iter().filter(|id| partition.contains(id)).count())
+            .collect()
+    }
+
+    #[test]
+    fn plans_partitions_from_the_histogram() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            let ids = insert_skewed_user_copies(conn);
+            diesel::sql_query("ANALYZE user_copies")
+                .execute(conn)
+                .unwrap();
+
+            let partitions =
+                diesel_streamer::plan_partitions!(user_copies, id, conn, partitions = 4);
+
+            assert_eq!(partitions.len(), 4);
+            assert_eq!(partitions.first().unwrap().start, 1);
+            assert_eq!(partitions.last().unwrap().end, 10_001);
+            assert!(partitions
+                .windows(2)
+                .all(|window| window[0].end == window[1].start));
+
+            for rows in rows_per_partition(&ids, &partitions) {
+                assert!(
+                    (9..=11).contains(&rows),
+                    "unbalanced partition of {rows} rows"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn plans_partitions_from_a_sample() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            let ids = insert_skewed_user_copies(conn);
+
+            let partitions = diesel_streamer::plan_partitions!(
+                user_copies,
+                id,
+                conn,
+                partitions = 4,
+                sample_percent = 100.0
+            );
+
+            assert_eq!(partitions.len(), 4);
+            assert_eq!(partitions.first().unwrap().start, 1);
+            assert_eq!(partitions.last().unwrap().end, 10_001);
+
+            for rows in rows_per_partition(&ids, &partitions) {
+                assert!(
+                    (9..=11).contains(&rows),
+                    "unbalanced partition of {rows} rows"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn plans_equal_width_partitions_without_statistics() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            factory::create_user_copies_table(conn);
+
+            let partitions =
+                diesel_streamer::plan_partitions!(user_copies, id, conn, partitions = 2);
+            assert_eq!(partitions, Vec::<Range<i32>>::new());
+
+            diesel::insert_into(user_copies)
+                .values(
+                    [3, 5, 8]
+                        .iter()
+                        .map(|&copy_id| NewUserCopy {
+                            id: copy_id,
+                            name: format!("Copy {copy_id}"),
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)
+                .unwrap();
+
+            let partitions =
+                diesel_streamer::plan_partitions!(user_copies, id, conn, partitions = 2);
+            assert_eq!(partitions, vec![3..6, 6..9]);
+        });
+    }
+
+    #[test]
+    fn keeps_the_transaction_when_statistics_cannot_be_read() {
+        test_runner::run_test(|conn| {
+            use self::user_copy_views::dsl::{id, user_copy_views};
+
+            insert_skewed_user_copies(conn);
+            diesel::sql_query("CREATE TEMPORARY VIEW user_copy_views AS SELECT * FROM user_copies")
+                .execute(conn)
+                .unwrap();
+
+            // views cannot be sampled, so the statistics query fails
+            let partitions = diesel_streamer::plan_partitions!(
+                user_copy_views,
+                id,
+                conn,
+                partitions = 2,
+                sample_percent = 100.0
+            );
+            assert_eq!(partitions, vec![1..5001, 5001..10_001]);
+
+            assert_eq!(user_copy_views.count().get_result::<i64>(conn).unwrap(), 40);
+        });
+    }
+
+    #[test]
+    fn streams_one_chunk_per_partition() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            let ids = insert_skewed_user_copies(conn);
+
+            let partitions = diesel_streamer::plan_partitions!(
+                user_copies,
+                id,
+                conn,
+                partitions = 4,
+                sample_percent = 100.0
+            );
+            let expected_chunk_sizes = rows_per_partition(&ids, &partitions);
+
+            let mut chunk_sizes = vec![];
+            let mut loaded_ids = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().partitions(partitions),
+                |chunk: Vec<UserCopy>| {
+                    chunk_sizes.push(chunk.len());
+                    loaded_ids.extend(chunk.into_iter().map(|copy| copy.id));
+                }
+            );
+
+            loaded_ids.sort_unstable();
+
+            assert_eq!(chunk_sizes, expected_chunk_sizes);
+            assert_eq!(loaded_ids, ids);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(10_001)
+                }
+            );
+        });
+    }
+}
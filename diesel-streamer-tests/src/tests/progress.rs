@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use diesel_streamer::{Progress, StreamOptions};
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[test]
+    fn reports_progress_after_each_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let all_users = factory::get_users(conn);
+            let first_id = all_users.first().unwrap().id;
+            let last_id = all_users.last().unwrap().id;
+
+            let reports = Arc::new(Mutex::new(vec![]));
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2).on_progress({
+                    let reports = reports.clone();
+
+                    move |progress: &Progress<i32>| reports.lock().unwrap().push(*progress)
+                }),
+                |_loaded_users: Vec<User>| {}
+            );
+
+            let reports = reports.lock().unwrap();
+
+            assert_eq!(reports.len(), 2);
+
+            assert_eq!(reports[0].cursor, first_id + 2);
+            assert_eq!((reports[0].from, reports[0].to), (first_id, last_id));
+            assert_eq!((reports[0].rows, reports[0].chunks), (2, 1));
+            assert!((reports[0].percent_complete() - 50.0).abs() < f64::EPSILON);
+
+            assert_eq!((reports[1].rows, reports[1].chunks), (4, 2));
+            assert!((reports[1].percent_complete() - 100.0).abs() < f64::EPSILON);
+            assert_eq!(reports[1].eta(), Some(Duration::ZERO));
+        });
+    }
+
+    #[test]
+    fn estimates_time_left_from_the_pace_so_far() {
+        let mut progress = Progress::start(1, 100);
+
+        assert_eq!(progress.eta(), None);
+
+        progress.record_chunk(26, 25);
+        progress.elapsed = Duration::from_secs(10);
+
+        assert!((progress.percent_complete() - 25.0).abs() < f64::EPSILON);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn moves_progress_bar_through_the_cursor_range() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let progress_bar = indicatif::ProgressBar::hidden();
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new()
+                    .chunk_size(3)
+                    .progress_bar(progress_bar.clone()),
+                |_loaded_users: Vec<User>| {}
+            );
+
+            assert_eq!(progress_bar.length(), Some(4));
+            assert_eq!(progress_bar.position(), 4);
+            assert_eq!(progress_bar.message(), "4 rows");
+        });
+    }
+}
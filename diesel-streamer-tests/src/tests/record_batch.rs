@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int32Type, Int64Type};
+    use arrow_array::{Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use diesel::prelude::*;
+    use diesel_streamer::{RecordBatchEncoder, StreamOptions};
+    use serde::Serialize;
+
+    use crate::factory;
+    use crate::test_runner;
+
+    #[derive(Debug, Queryable, Serialize)]
+    struct UserRow {
+        id: i32,
+        name: String,
+    }
+
+    fn names(record_batch: &RecordBatch) -> Vec<&str> {
+        record_batch
+            .column_by_name("name")
+            .unwrap()
+            .as_string::<i32>()
+            .iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn encodes_each_chunk_with_the_schema_of_the_columns() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut encoder = RecordBatchEncoder::for_columns((id, name));
+            let mut record_batches = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(2),
+                |user_rows: Vec<UserRow>| record_batches.push(encoder.encode(&user_rows).unwrap())
+            );
+
+            assert_eq!(
+                encoder.schema().unwrap().as_ref(),
+                &Schema::new(vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("name", DataType::Utf8, false),
+                ])
+            );
+
+            assert_eq!(record_batches.len(), 2);
+            assert_eq!(
+                record_batches[0]
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .values(),
+                &[all_users[0].id, all_users[1].id]
+            );
+            assert_eq!(names(&record_batches[0]), ["UserName 1", "UserName 2"]);
+            assert_eq!(names(&record_batches[1]), ["UserName 3"]);
+        });
+    }
+
+    #[test]
+    fn infers_the_schema_from_the_rows() {
+        let mut encoder = RecordBatchEncoder::infer();
+
+        assert_eq!(encoder.encode::<UserRow>(&[]).unwrap().num_rows(), 0);
+        assert!(encoder.schema().is_none());
+
+        let record_batch = encoder
+            .encode(&[UserRow {
+                id: 7,
+                name: "Ada Lovelace".to_string(),
+            }])
+            .unwrap();
+
+        assert_eq!(
+            record_batch.schema().as_ref(),
+            &Schema::new(vec![
+                Field::new("id", DataType::Int64, true),
+                Field::new("name", DataType::Utf8, true),
+            ])
+        );
+        assert_eq!(
+            record_batch.column(0).as_primitive::<Int64Type>().values(),
+            &[7]
+        );
+        assert_eq!(names(&record_batch), ["Ada Lovelace"]);
+    }
+
+    #[test]
+    fn encodes_bytea_columns_as_binary() {
+        table! {
+            attachments (id) {
+                id -> Int4,
+                content -> Nullable<Bytea>,
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Attachment {
+            id: i32,
+            content: Option<Vec<u8>>,
+        }
+
+        let mut encoder = RecordBatchEncoder::for_columns((attachments::id, attachments::content));
+
+        let record_batch = encoder
+            .encode(&[
+                Attachment {
+                    id: 1,
+                    content: Some(b"%PDF".to_vec()),
+                },
+                Attachment {
+                    id: 2,
+                    content: None,
+                },
+                Attachment {
+                    id: 3,
+                    content: Some(vec![]),
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(
+            record_batch.schema().field(1),
+            &Field::new("content", DataType::Binary, true)
+        );
+
+        let contents = record_batch.column(1).as_binary::<i32>();
+
+        assert_eq!(contents.value(0), b"%PDF");
+        assert!(contents.is_null(1));
+        assert_eq!(contents.value(2), b"");
+    }
+}
@@ -1,6 +1,12 @@
 #[cfg(test)]
 mod tests {
-    use crate::factory::{self, User};
+    use std::ops::ControlFlow;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use diesel_streamer::{StreamEnd, StreamOptions};
+
+    use crate::factory::{self, User, UserName};
     use crate::test_runner;
 
     #[test]
@@ -130,4 +136,365 @@ mod tests {
             );
         });
     }
+
+    #[test]
+    fn skips_rows_inserted_while_streaming_by_default() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+
+            let mut streamed_names = vec![];
+
+            diesel_streamer::stream_serial_table!(users, id, conn, 100, |loaded_users: Vec<
+                User,
+            >| {
+                factory::insert_user("Late User", conn);
+
+                streamed_names.extend(loaded_users.into_iter().map(|user| user.name));
+            });
+
+            assert_eq!(streamed_names.len(), 2);
+            assert!(!streamed_names.contains(&"Late User".to_string()));
+        });
+    }
+
+    #[test]
+    fn processes_rows_inserted_while_streaming_with_moving_upper_bound() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+
+            let mut streamed_names = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().chunk_size(100).moving_upper_bound(),
+                |loaded_users: Vec<User>| {
+                    if streamed_names.is_empty() {
+                        factory::insert_user("Late User", conn);
+                    }
+
+                    streamed_names.extend(loaded_users.into_iter().map(|user| user.name));
+                }
+            );
+
+            assert_eq!(streamed_names.len(), 3);
+            assert_eq!(streamed_names.last().unwrap(), "Late User");
+        });
+    }
+
+    #[test]
+    fn stops_refreshing_upper_bound_after_max_refreshes() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(1, conn);
+
+            let mut call_count = 0;
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new()
+                    .chunk_size(100)
+                    .max_upper_bound_refreshes(2),
+                |_loaded_users: Vec<User>| {
+                    call_count += 1;
+
+                    factory::insert_user("Late User", conn);
+                }
+            );
+
+            assert_eq!(call_count, 3);
+        });
+    }
+
+    #[test]
+    fn returns_cursor_after_the_end_when_completed() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+
+            let last_user = factory::get_users(conn).pop().unwrap();
+
+            let stream_end =
+                diesel_streamer::stream_serial_table!(users, id, conn, |_loaded_users: Vec<
+                    User,
+                >| {});
+
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(last_user.id + 1)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn stops_between_chunks_when_cancelled() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let all_users = factory::get_users(conn);
+            let cancellation = Arc::new(AtomicBool::new(false));
+            let mut streamed_users = vec![];
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new()
+                    .chunk_size(1)
+                    .cancellation(cancellation.clone()),
+                |loaded_users: Vec<User>| {
+                    streamed_users.extend(loaded_users);
+
+                    if streamed_users.len() == 2 {
+                        cancellation.store(true, Ordering::SeqCst);
+                    }
+                }
+            );
+
+            assert_eq!(streamed_users, all_users[..2]);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Cancelled {
+                    cursor: Some(all_users[2].id)
+                }
+            );
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                1,
+                stream_end.cursor(),
+                |loaded_users: Vec<User>| {
+                    streamed_users.extend(loaded_users);
+                }
+            );
+
+            assert_eq!(streamed_users, all_users);
+        });
+    }
+
+    #[test]
+    fn does_nothing_when_cancelled_before_the_first_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+
+            let first_user = factory::get_users(conn).remove(0);
+            let mut call_count = 0;
+
+            let stream_end = diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                options = StreamOptions::new().cancellation(Arc::new(AtomicBool::new(true))),
+                |_loaded_users: Vec<User>| {
+                    call_count += 1;
+                }
+            );
+
+            assert_eq!(call_count, 0);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Cancelled {
+                    cursor: Some(first_user.id)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn stops_when_processor_breaks() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let all_users = factory::get_users(conn);
+            let mut call_count = 0;
+
+            let stream_end =
+                diesel_streamer::stream_serial_table!(users, id, conn, 1, |loaded_users: Vec<
+                    User,
+                >| {
+                    call_count += 1;
+
+                    match loaded_users
+                        .into_iter()
+                        .find(|user| user.name == "UserName 2")
+                    {
+                        Some(user) => ControlFlow::Break(user),
+                        None => ControlFlow::Continue(()),
+                    }
+                });
+
+            assert_eq!(call_count, 2);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Stopped {
+                    cursor: Some(all_users[1].id),
+                    reason: all_users[1].clone()
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn stops_when_processor_fails() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let all_users = factory::get_users(conn);
+            let mut call_count = 0;
+
+            let stream_end =
+                diesel_streamer::stream_serial_table!(users, id, conn, 2, |_loaded_users: Vec<
+                    User,
+                >| {
+                    call_count += 1;
+
+                    if call_count == 2 {
+                        Err("failed to process chunk")
+                    } else {
+                        Ok(())
+                    }
+                });
+
+            assert_eq!(call_count, 2);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Stopped {
+                    cursor: Some(all_users[2].id),
+                    reason: "failed to process chunk"
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn loads_only_the_selected_columns() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut loaded_names = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                users.filter(name.ne("UserName 2")),
+                id,
+                conn,
+                select = (id, name),
+                options = StreamOptions::new().chunk_size(2),
+                |loaded_users: Vec<(i32, String)>| loaded_names.extend(loaded_users)
+            );
+
+            assert_eq!(
+                loaded_names,
+                vec![
+                    (all_users[0].id, all_users[0].name.clone()),
+                    (all_users[2].id, all_users[2].name.clone()),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn loads_selectable_rows() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(2, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut loaded_names = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                users,
+                id,
+                conn,
+                select = UserName::as_select(),
+                |loaded_users: Vec<UserName>| loaded_names.extend(loaded_users)
+            );
+
+            assert_eq!(
+                loaded_names,
+                all_users
+                    .iter()
+                    .map(|user| UserName {
+                        id: user.id,
+                        name: user.name.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn overrides_the_select_clause_of_the_query() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut loaded_rows = vec![];
+
+            // the query's own select clause is used when `select` is not given
+            let stream_end = diesel_streamer::stream_serial_table!(
+                users.select((id, name)),
+                id,
+                conn,
+                2,
+                |loaded_users: Vec<(i32, String)>| loaded_rows.extend(loaded_users)
+            );
+
+            assert_eq!(
+                loaded_rows,
+                all_users
+                    .iter()
+                    .map(|user| (user.id, user.name.clone()))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(all_users[2].id + 1)
+                }
+            );
+
+            let mut loaded_ids = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                users.select((id, name)).filter(name.ne("UserName 2")),
+                id,
+                conn,
+                select = id,
+                options = StreamOptions::new().chunk_size(2),
+                |loaded_users: Vec<i32>| loaded_ids.extend(loaded_users)
+            );
+
+            assert_eq!(loaded_ids, vec![all_users[0].id, all_users[2].id]);
+        });
+    }
 }
@@ -0,0 +1,261 @@
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use diesel::prelude::*;
+    use diesel_streamer::{LeaseCoordinator, Shard, ShardError, StreamOptions};
+
+    use crate::factory::{self, NewUserCopy, User, UserCopy};
+    use crate::test_runner;
+
+    fn stream_shard(shard: Shard, conn: &mut PgConnection) -> Vec<User> {
+        use factory::users::dsl::{id, users};
+
+        let mut loaded_users = vec![];
+
+        diesel_streamer::stream_serial_table!(
+            users,
+            id,
+            conn,
+            options = StreamOptions::new().chunk_size(4).shard(shard),
+            |chunk: Vec<User>| loaded_users.extend(chunk)
+        );
+
+        loaded_users
+    }
+
+    #[test]
+    fn splits_rows_by_cursor_modulo() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(10, conn);
+
+            let mut loaded_users = vec![];
+
+            for index in 0..3 {
+                let shard_users = stream_shard(Shard::new(index, 3), conn);
+
+                assert!(!shard_users.is_empty());
+                assert!(shard_users
+                    .iter()
+                    .all(|user| user.id.rem_euclid(3) == i32::try_from(index).unwrap()));
+
+                loaded_users.extend(shard_users);
+            }
+
+            loaded_users.sort_by_key(|user| user.id);
+
+            assert_eq!(loaded_users, factory::get_users(conn));
+        });
+    }
+
+    #[test]
+    fn splits_rows_by_hashed_key() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(10, conn);
+
+            let first_shard = stream_shard(Shard::new(0, 2).hashed_on("name"), conn);
+            let second_shard = stream_shard(Shard::new(1, 2).hashed_on("name"), conn);
+
+            assert_eq!(
+                stream_shard(Shard::new(0, 2).hashed_on("name"), conn),
+                first_shard
+            );
+
+            let mut loaded_users = first_shard;
+            loaded_users.extend(second_shard);
+            loaded_users.sort_by_key(|user| user.id);
+
+            assert_eq!(loaded_users, factory::get_users(conn));
+        });
+    }
+
+    #[test]
+    fn assigns_negative_cursors_to_shards() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, user_copies};
+
+            factory::create_user_copies_table(conn);
+            diesel::insert_into(user_copies)
+                .values(
+                    (-4..4)
+                        .map(|copy_id| NewUserCopy {
+                            id: copy_id,
+                            name: format!("Copy {copy_id}"),
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)
+                .unwrap();
+
+            let mut loaded_ids = vec![];
+
+            diesel_streamer::stream_serial_table!(
+                user_copies,
+                id,
+                conn,
+                options = StreamOptions::new().shard(Shard::new(1, 2)),
+                |chunk: Vec<UserCopy>| loaded_ids.extend(chunk.into_iter().map(|copy| copy.id))
+            );
+
+            assert_eq!(loaded_ids, vec![-3, -1, 1, 3]);
+        });
+    }
+
+    #[test]
+    fn only_writes_the_rows_of_the_shard() {
+        test_runner::run_test(|conn| {
+            use factory::user_copies::dsl::{id, name, user_copies};
+            use factory::users::dsl::{id as user_id, name as user_name, users};
+
+            factory::insert_users(10, conn);
+            factory::create_user_copies_table(conn);
+
+            let all_users = factory::get_users(conn);
+            let in_shard = |cursor: i32| cursor.rem_euclid(2) == 1;
+
+            let batch_end = diesel_streamer::update_in_batches!(
+                users,
+                user_id,
+                conn,
+                user_name.eq("updated"),
+                options = StreamOptions::new().chunk_size(4).shard(Shard::new(1, 2))
+            )
+            .unwrap();
+
+            let updated_users = factory::get_users(conn);
+
+            assert_eq!(
+                batch_end.affected_rows,
+                all_users.iter().filter(|user| in_shard(user.id)).count()
+            );
+            for (user, updated_user) in all_users.iter().zip(&updated_users) {
+                if in_shard(user.id) {
+                    assert_eq!(updated_user.name, "updated");
+                } else {
+                    assert_eq!(updated_user.name, user.name);
+                }
+            }
+
+            diesel_streamer::copy_table!(
+                users,
+                user_id,
+                conn,
+                into = user_copies,
+                options = StreamOptions::new().chunk_size(4).shard(Shard::new(1, 2)),
+                |user: User| NewUserCopy {
+                    id: user.id,
+                    name: user.name,
+                }
+            )
+            .unwrap();
+
+            let copied_ids = user_copies
+                .select(id)
+                .order_by(id)
+                .load::<i32>(conn)
+                .unwrap();
+
+            assert!(!copied_ids.is_empty());
+            assert!(copied_ids.iter().all(|&copy_id| in_shard(copy_id)));
+
+            diesel_streamer::delete_in_batches!(
+                user_copies.filter(name.eq("updated")),
+                id,
+                conn,
+                options = StreamOptions::new().shard(Shard::new(0, 2))
+            )
+            .unwrap();
+
+            // the other shard holds none of the copies
+            assert_eq!(
+                user_copies.count().get_result::<i64>(conn).unwrap(),
+                i64::try_from(copied_ids.len()).unwrap()
+            );
+
+            diesel_streamer::delete_in_batches!(
+                users,
+                user_id,
+                conn,
+                options = StreamOptions::new().chunk_size(4).shard(Shard::new(1, 2))
+            )
+            .unwrap();
+
+            let remaining_users = factory::get_users(conn);
+
+            assert!(!remaining_users.is_empty());
+            assert!(remaining_users.iter().all(|user| !in_shard(user.id)));
+        });
+    }
+
+    #[test]
+    fn only_leases_the_rows_of_the_shard() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(10, conn);
+
+            let all_users = factory::get_users(conn);
+            let units = vec![
+                all_users[0].id..all_users[5].id,
+                all_users[5].id..all_users[9].id + 1,
+            ];
+            let coordinator =
+                LeaseCoordinator::new("backfill_users_1_of_3", "worker-1").shard(Shard::new(1, 3));
+
+            coordinator.create_table_query().execute(conn).unwrap();
+            coordinator.plan_query(&units).execute(conn).unwrap();
+
+            let mut loaded_users = vec![];
+
+            diesel_streamer::stream_leased_table!(
+                users,
+                id,
+                conn,
+                coordinator = coordinator,
+                chunk_size = 4,
+                |chunk: Vec<User>| loaded_users.extend(chunk)
+            )
+            .unwrap();
+
+            assert_eq!(
+                loaded_users,
+                all_users
+                    .into_iter()
+                    .filter(|user| user.id.rem_euclid(3) == 1)
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn reads_shards_from_the_environment() {
+        env::set_var(Shard::COUNT_VARIABLE, "4");
+        env::set_var(Shard::INDEX_VARIABLE, "2");
+        assert_eq!(Shard::from_env(), Ok(Shard::new(2, 4)));
+
+        env::remove_var(Shard::INDEX_VARIABLE);
+        env::set_var("HOSTNAME", "backfill-3");
+        assert_eq!(Shard::from_env(), Ok(Shard::new(3, 4)));
+
+        env::set_var("HOSTNAME", "backfill-7");
+        assert_eq!(
+            Shard::from_env(),
+            Err(ShardError::OutOfRange { index: 7, count: 4 })
+        );
+
+        env::set_var(Shard::COUNT_VARIABLE, "four");
+        assert_eq!(
+            Shard::from_env(),
+            Err(ShardError::Invalid(
+                Shard::COUNT_VARIABLE,
+                "four".to_owned()
+            ))
+        );
+
+        env::remove_var(Shard::COUNT_VARIABLE);
+        assert_eq!(
+            Shard::from_env(),
+            Err(ShardError::Missing(Shard::COUNT_VARIABLE))
+        );
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use diesel_streamer::{StreamEnd, StreamOptions};
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[test]
+    fn streams_the_declared_table_by_the_declared_cursor() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+            let last_user = all_users.last().unwrap();
+
+            let mut streamed_users = vec![];
+
+            let stream_end = User::stream(conn)
+                .chunk_size(2)
+                .for_each(|users| streamed_users.extend(users));
+
+            assert_eq!(streamed_users, all_users);
+            assert_eq!(
+                stream_end,
+                StreamEnd::Completed {
+                    cursor: Some(last_user.id + 1)
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn streams_between_the_given_bounds() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let mut streamed_users = vec![];
+
+            User::stream(conn)
+                .from(all_users[1].id)
+                .to(all_users[3].id)
+                .for_each(|users| streamed_users.extend(users));
+
+            assert_eq!(streamed_users, all_users[1..=3]);
+        });
+    }
+
+    #[test]
+    fn stops_on_the_processor_break() {
+        test_runner::run_test(|conn| {
+            factory::insert_users(5, conn);
+
+            let all_users = factory::get_users(conn);
+
+            let stream_end = User::stream(conn)
+                .options(StreamOptions::new().chunk_size(1).name("users"))
+                .for_each(|users| match users.first() {
+                    Some(user) if user.id == all_users[2].id => ControlFlow::Break(user.id),
+                    _ => ControlFlow::Continue(()),
+                });
+
+            assert_eq!(
+                stream_end,
+                StreamEnd::Stopped {
+                    cursor: Some(all_users[2].id),
+                    reason: all_users[2].id
+                }
+            );
+        });
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    use crate::factory::{self, User};
+    use crate::test_runner;
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Logs {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn records_spans_for_the_stream_and_each_chunk() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let logs = Logs::default();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::TRACE)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_ansi(false)
+                .with_writer({
+                    let logs = logs.clone();
+
+                    move || logs.clone()
+                })
+                .finish();
+
+            tracing::subscriber::with_default(subscriber, || {
+                diesel_streamer::stream_serial_table!(users, id, conn, 2, |_loaded_users: Vec<
+                    User,
+                >| {
+                    tracing::info!("processing chunk");
+                });
+            });
+
+            let logs = logs.contents();
+
+            assert_eq!(logs.matches("resolved stream bounds").count(), 1);
+            assert_eq!(logs.matches("loading chunk").count(), 2);
+            assert_eq!(logs.matches("chunk{").count(), 2 * 3);
+            assert!(logs.contains(r#"SELECT "users"."id""#));
+            assert!(logs.contains("rows=2"));
+            assert!(logs.contains("query_duration="));
+            assert!(logs.contains("processor_duration="));
+            assert!(logs.contains("stream_serial_table{from="));
+        });
+    }
+
+    #[test]
+    fn records_one_stream_span_for_a_leased_stream() {
+        test_runner::run_test(|conn| {
+            use diesel::RunQueryDsl;
+            use diesel_streamer::LeaseCoordinator;
+            use factory::users::dsl::{id, users};
+
+            factory::insert_users(4, conn);
+
+            let user_ids: Vec<_> = factory::get_users(conn)
+                .iter()
+                .map(|user| user.id)
+                .collect();
+            let coordinator = LeaseCoordinator::new("backfill_users", "worker-1");
+            coordinator.create_table_query().execute(conn).unwrap();
+            coordinator
+                .plan_query(&[user_ids[0]..user_ids[2], user_ids[2]..user_ids[3] + 1])
+                .execute(conn)
+                .unwrap();
+
+            let logs = Logs::default();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::TRACE)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_ansi(false)
+                .with_writer({
+                    let logs = logs.clone();
+
+                    move || logs.clone()
+                })
+                .finish();
+
+            tracing::subscriber::with_default(subscriber, || {
+                diesel_streamer::stream_leased_table!(
+                    users,
+                    id,
+                    conn,
+                    coordinator = coordinator,
+                    chunk_size = 1,
+                    |_loaded_users: Vec<User>| {}
+                )
+                .unwrap();
+            });
+
+            let logs = logs.contents();
+
+            assert_eq!(logs.matches("loading chunk").count(), 4);
+            assert_eq!(
+                logs.lines()
+                    .filter(|line| line.contains("close") && !line.contains(":chunk{"))
+                    .count(),
+                1
+            );
+        });
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use diesel::prelude::*;
+    use diesel::result::{DatabaseErrorKind, Error};
+    use diesel_streamer::{BatchEnd, StreamEnd, StreamOptions};
+
+    use crate::test_runner;
+    use crate::{db, factory};
+
+    table! {
+        locked_users (id) {
+            id -> Integer,
+            name -> Varchar,
+        }
+    }
+
+    #[test]
+    fn updates_every_row_in_chunks() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(5, conn);
+
+            let last_user = factory::get_users(conn).pop().unwrap();
+
+            let batch_end = diesel_streamer::update_in_batches!(
+                users,
+                id,
+                conn,
+                name.eq("Anonymous"),
+                options = StreamOptions::new().chunk_size(2)
+            )
+            .unwrap();
+
+            assert_eq!(
+                batch_end,
+                BatchEnd {
+                    affected_rows: 5,
+                    stream_end: StreamEnd::Completed {
+                        cursor: Some(last_user.id + 1)
+                    }
+                }
+            );
+            assert!(factory::get_users(conn)
+                .iter()
+                .all(|user| user.name == "Anonymous"));
+        });
+    }
+
+    #[test]
+    fn updates_only_filtered_rows_within_bounds() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(6, conn);
+
+            let all_users = factory::get_users(conn);
+            let from = all_users[1].id;
+            let to = all_users[4].id;
+
+            let batch_end = diesel_streamer::update_in_batches!(
+                users.filter(name.ne("UserName 3")),
+                id,
+                conn,
+                name.eq("Anonymous"),
+                options = StreamOptions::new().chunk_size(2).from(from).to(to)
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 3);
+
+            let names: Vec<String> = factory::get_users(conn)
+                .into_iter()
+                .map(|user| user.name)
+                .collect();
+
+            assert_eq!(
+                names,
+                [
+                    "UserName 1",
+                    "Anonymous",
+                    "UserName 3",
+                    "Anonymous",
+                    "Anonymous",
+                    "UserName 6"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn updates_each_chunk_in_its_own_transaction() {
+        test_runner::run_test(|conn| {
+            use factory::users::dsl::{id, name, users};
+
+            factory::insert_users(3, conn);
+
+            let batch_end = diesel_streamer::update_in_batches!(
+                users,
+                id,
+                conn,
+                name.eq("Anonymous"),
+                options = StreamOptions::new()
+                    .chunk_size(1)
+                    .lock_timeout(Duration::from_millis(500))
+            )
+            .unwrap();
+
+            assert_eq!(batch_end.affected_rows, 3);
+            assert!(batch_end.stream_end.cursor().is_some());
+            assert_eq!(
+                users
+                    .filter(name.eq("Anonymous"))
+                    .count()
+                    .get_result::<i64>(conn)
+                    .unwrap(),
+                3
+            );
+        });
+    }
+
+    #[test]
+    fn ends_with_the_chunk_whose_locks_timed_out() {
+        use self::locked_users::dsl::{id, locked_users, name};
+
+        // Rows locked by another session must be committed, so they live in
+        // their own table rather than in the test transaction's `users`
+        let mut locking_conn = db::establish_connection();
+        diesel::sql_query(
+            "CREATE TABLE IF NOT EXISTS locked_users (id SERIAL PRIMARY KEY, name VARCHAR NOT NULL)",
+        )
+        .execute(&mut locking_conn)
+        .unwrap();
+        let locked_ids = diesel::insert_into(locked_users)
+            .values(&vec![name.eq("UserName 1"), name.eq("UserName 2")])
+            .returning(id)
+            .get_results::<i32>(&mut locking_conn)
+            .unwrap();
+
+        locking_conn
+            .transaction::<_, Error, _>(|locking_conn| {
+                locked_users
+                    .find(locked_ids[1])
+                    .for_update()
+                    .select(id)
+                    .get_result::<i32>(locking_conn)?;
+
+                test_runner::run_test(|conn| {
+                    let batch_error = diesel_streamer::update_in_batches!(
+                        locked_users,
+                        id,
+                        conn,
+                        name.eq("Anonymous"),
+                        options = StreamOptions::new()
+                            .chunk_size(1)
+                            .from(locked_ids[0])
+                            .to(locked_ids[1])
+                            .lock_timeout(Duration::from_millis(50))
+                    )
+                    .unwrap_err();
+
+                    assert_eq!(batch_error.chunk, locked_ids[1]..=locked_ids[1]);
+                    assert_eq!(batch_error.affected_rows, 1);
+                    assert!(matches!(
+                        batch_error.error,
+                        Error::DatabaseError(DatabaseErrorKind::Unknown, ref info)
+                            if info.message().contains("lock timeout")
+                    ));
+                });
+
+                Ok(())
+            })
+            .unwrap();
+
+        diesel::delete(locked_users.filter(id.eq_any(locked_ids)))
+            .execute(&mut locking_conn)
+            .unwrap();
+    }
+}
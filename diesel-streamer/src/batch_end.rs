@@ -0,0 +1,95 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::StreamEnd;
+
+/// How a batched write, such as `update_in_batches!` or
+/// `delete_in_batches!`, ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchEnd<T, B = Infallible> {
+    /// Rows affected across all chunks.
+    pub affected_rows: usize,
+    /// Where the write stopped. Chunks below its cursor have been written.
+    pub stream_end: StreamEnd<T, B>,
+}
+
+/// A batched write that failed on a chunk, e.g. because the chunk's locks
+/// could not be acquired within its
+/// [`lock_timeout`](crate::StreamOptions::lock_timeout). Chunks below it
+/// have been written.
+#[derive(Debug)]
+pub struct BatchError<T> {
+    /// The chunk that failed. Resume `from` its start to retry it, or from
+    /// past its end to skip it.
+    pub chunk: RangeInclusive<T>,
+    /// Rows affected by the chunks written before it.
+    pub affected_rows: usize,
+    pub error: diesel::result::Error,
+}
+
+impl<T: fmt::Debug> fmt::Display for BatchError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "batched write failed on chunk {:?}..={:?}: {}",
+            self.chunk.start(),
+            self.chunk.end(),
+            self.error
+        )
+    }
+}
+
+impl<T: fmt::Debug> Error for BatchError<T> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Why a batched write's chunk broke the stream: either its statement failed,
+/// or its processor stopped the write.
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum __ChunkError<B> {
+    Query(diesel::result::Error),
+    Stopped(B),
+}
+
+impl<B> From<diesel::result::Error> for __ChunkError<B> {
+    fn from(error: diesel::result::Error) -> Self {
+        __ChunkError::Query(error)
+    }
+}
+
+/// Turns how the chunks of a batched write ended, each break carrying the end
+/// of the chunk it happened on, into the write's result.
+#[doc(hidden)]
+pub fn __batch_result<T: Copy, B>(
+    affected_rows: usize,
+    stream_end: StreamEnd<T, (T, __ChunkError<B>)>,
+) -> Result<BatchEnd<T, B>, BatchError<T>> {
+    let stream_end = match stream_end {
+        StreamEnd::Completed { cursor } => StreamEnd::Completed { cursor },
+        StreamEnd::Cancelled { cursor } => StreamEnd::Cancelled { cursor },
+        StreamEnd::Stopped {
+            cursor,
+            reason: (_, __ChunkError::Stopped(reason)),
+        } => StreamEnd::Stopped { cursor, reason },
+        StreamEnd::Stopped {
+            cursor,
+            reason: (chunk_to, __ChunkError::Query(error)),
+        } => {
+            return Err(BatchError {
+                chunk: cursor.unwrap_or(chunk_to)..=chunk_to,
+                affected_rows,
+                error,
+            })
+        }
+    };
+
+    Ok(BatchEnd {
+        affected_rows,
+        stream_end,
+    })
+}
@@ -0,0 +1,13 @@
+use std::process;
+
+use clap::Parser;
+use diesel_streamer::cli::{self, Args};
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(error) = cli::run(&args) {
+        eprintln!("diesel-streamer: {error}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,238 @@
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use std::any::TypeId;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use diesel::backend::Backend;
+use diesel::query_builder::{QueryBuilder, QueryFragment, SqlQuery};
+use diesel::sql_types::{BigInt, Nullable};
+use diesel::QueryableByName;
+
+/// Decides how the cursor's bounds are resolved when `from` or `to` is not
+/// set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bounds {
+    /// Through `MIN`/`MAX` queries on the cursor field.
+    Exact,
+    /// Estimated from the statistics of the cursor field's table, without
+    /// scanning it:
+    ///
+    /// - Postgres: the start and `last_value` of the column's sequence,
+    ///   widened by the first and last `pg_stats.histogram_bounds`
+    /// - SQLite: 1 and the `sqlite_sequence` of `AUTOINCREMENT` tables
+    ///
+    /// Estimates cover the whole table, ignoring the query's filters. As
+    /// statistics may be stale, a bound cannot be estimated without a
+    /// sequence. Such a bound, e.g. on a column without a sequence or on
+    /// another backend, goes through `MIN`/`MAX` when `fallback_to_exact` is
+    /// set, and ends the stream as if the table were empty otherwise.
+    ///
+    /// A Postgres sequence does not see rows inserted with explicit ids: those
+    /// above its `last_value` and newer than the statistics lie past the
+    /// estimated upper bound and are not streamed. Tables written that way
+    /// need `Exact` bounds or an explicit `to`.
+    Estimated { fallback_to_exact: bool },
+}
+
+/// Bounds of a cursor field estimated from table statistics.
+#[doc(hidden)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, QueryableByName)]
+pub struct BoundsEstimate {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub from_estimate: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub to_estimate: Option<i64>,
+    /// Postgres' `pg_class.reltuples`, unless the table was never analyzed.
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub rows_estimate: Option<i64>,
+}
+
+impl BoundsEstimate {
+    #[must_use]
+    pub fn lower_bound<T: TryFrom<i64>>(&self) -> Option<T> {
+        self.from_estimate.and_then(|from| T::try_from(from).ok())
+    }
+
+    #[must_use]
+    pub fn upper_bound<T: TryFrom<i64>>(&self) -> Option<T> {
+        self.to_estimate.and_then(|to| T::try_from(to).ok())
+    }
+}
+
+/// Lower bound: the smallest of the sequence's start and the lowest
+/// histogram bound. Upper bound: the largest of the sequence's last value
+/// and the highest histogram bound. Either is `NULL` without its sequence
+/// value, as histograms alone may be stale.
+///
+/// An error would abort the caller's transaction, so the query steers clear
+/// of sequences it may not read and of histograms that are not integers.
+#[cfg(feature = "postgres")]
+fn postgres_estimate(table: &str, column: &str) -> String {
+    format!(
+        r"
+SELECT
+    CASE WHEN sequence.start_value IS NOT NULL
+        THEN LEAST(sequence.start_value, statistics.lower_bound)
+    END AS from_estimate,
+    CASE WHEN sequence.last_value IS NOT NULL
+        THEN GREATEST(sequence.last_value, statistics.upper_bound)
+    END AS to_estimate,
+    CASE WHEN class.reltuples < 0 THEN NULL ELSE class.reltuples::BIGINT END AS rows_estimate
+FROM pg_class AS class
+LEFT JOIN LATERAL (
+    SELECT
+        seqstart AS start_value,
+        CASE WHEN has_sequence_privilege(seqrelid, 'SELECT,USAGE')
+            THEN pg_sequence_last_value(seqrelid)
+        END AS last_value
+    FROM pg_sequence
+    WHERE seqrelid = pg_get_serial_sequence({table}, {column})::REGCLASS
+) AS sequence ON TRUE
+LEFT JOIN LATERAL (
+    SELECT bounds[1] AS lower_bound, bounds[cardinality(bounds)] AS upper_bound
+    FROM (
+        SELECT histogram_bounds::TEXT::BIGINT[] AS bounds
+        FROM pg_stats
+        WHERE (quote_ident(schemaname) || '.' || quote_ident(tablename))::REGCLASS = class.oid
+            AND attname = {column}
+            AND histogram_bounds::TEXT ~ '^\{{-?[0-9]+(,-?[0-9]+)*\}}$'
+        ORDER BY inherited
+        LIMIT 1
+    ) AS histogram
+) AS statistics ON TRUE
+WHERE class.oid = {table}::REGCLASS
+",
+        table = quote_literal(table),
+        column = quote_literal(column),
+    )
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_estimate(table: &str) -> String {
+    format!(
+        r"
+SELECT 1 AS from_estimate, seq AS to_estimate, NULL AS rows_estimate
+FROM sqlite_sequence
+WHERE name = {table}
+",
+        table = quote_literal(table),
+    )
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quotes Postgres identifiers, e.g. `"schema"."table"`.
+#[cfg(feature = "postgres")]
+pub(crate) fn quote_identifiers(identifiers: &[String]) -> String {
+    identifiers
+        .iter()
+        .map(|identifier| format!("\"{}\"", identifier.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The query estimating the bounds of `cursor_field`, if `DB` has
+/// statistics to estimate them from.
+#[doc(hidden)]
+pub fn __estimate_bounds_query<DB, C>(
+    cursor_field: &C,
+    _backend: PhantomData<DB>,
+) -> Option<SqlQuery>
+where
+    DB: Backend + Default + 'static,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    let (table_identifiers, column) = cursor_identifiers(cursor_field)?;
+
+    estimate_query::<DB>(table_identifiers, &column)
+}
+
+/// The identifiers of the table of `cursor_field`, e.g. its schema and name,
+/// along with the name of the column.
+pub(crate) fn cursor_identifiers<DB, C>(cursor_field: &C) -> Option<(Vec<String>, String)>
+where
+    DB: Backend + Default,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    let mut query_builder = DB::QueryBuilder::default();
+    cursor_field
+        .to_sql(&mut query_builder, &DB::default())
+        .ok()?;
+
+    let mut identifiers = parse_identifiers(&query_builder.finish());
+    let column = identifiers.pop()?;
+
+    if identifiers.is_empty() {
+        None
+    } else {
+        Some((identifiers, column))
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+#[cfg_attr(not(feature = "sqlite"), allow(unused_mut))]
+fn estimate_query<DB: 'static>(
+    mut table_identifiers: Vec<String>,
+    column: &str,
+) -> Option<SqlQuery> {
+    #[cfg(feature = "postgres")]
+    {
+        if TypeId::of::<DB>() == TypeId::of::<diesel::pg::Pg>() {
+            let table = quote_identifiers(&table_identifiers);
+
+            return Some(diesel::sql_query(postgres_estimate(&table, column)));
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    {
+        if TypeId::of::<DB>() == TypeId::of::<diesel::sqlite::Sqlite>() {
+            let table = table_identifiers.pop()?;
+
+            return Some(diesel::sql_query(sqlite_estimate(&table)));
+        }
+    }
+
+    None
+}
+
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn estimate_query<DB>(_table_identifiers: Vec<String>, _column: &str) -> Option<SqlQuery> {
+    None
+}
+
+/// Splits a rendered column such as `"schema"."table"."column"` into its
+/// unquoted identifiers.
+fn parse_identifiers(sql: &str) -> Vec<String> {
+    let mut identifiers = vec![];
+    let mut chars = sql.chars().peekable();
+
+    while let Some(quote) = chars.next() {
+        if quote == '.' {
+            continue;
+        }
+
+        let mut identifier = String::new();
+
+        while let Some(c) = chars.next() {
+            if c != quote {
+                identifier.push(c);
+            } else if chars.peek() == Some(&quote) {
+                identifier.push(chars.next().unwrap_or(quote));
+            } else {
+                break;
+            }
+        }
+
+        identifiers.push(identifier);
+    }
+
+    identifiers
+}
@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle checked between chunks to stop a running stream.
+///
+/// The chunk in flight is always finished before the stream stops, so the
+/// cursor returned in [`StreamEnd::Cancelled`](crate::StreamEnd::Cancelled)
+/// can be used to resume later on.
+///
+/// Implemented for `AtomicBool` (and `Arc<AtomicBool>`) for synchronous
+/// streams and for `tokio_util::sync::CancellationToken` when the `async`
+/// feature is enabled.
+pub trait Cancellation {
+    fn is_cancelled(&self) -> bool;
+}
+
+impl Cancellation for AtomicBool {
+    fn is_cancelled(&self) -> bool {
+        self.load(Ordering::SeqCst)
+    }
+}
+
+impl<C: Cancellation + ?Sized> Cancellation for Arc<C> {
+    fn is_cancelled(&self) -> bool {
+        (**self).is_cancelled()
+    }
+}
+
+#[cfg(feature = "async")]
+impl Cancellation for tokio_util::sync::CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        tokio_util::sync::CancellationToken::is_cancelled(self)
+    }
+}
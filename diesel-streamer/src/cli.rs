@@ -0,0 +1,344 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, ValueEnum};
+use diesel::sql_types::{Array, BigInt, Nullable, Text};
+use diesel::{Connection, PgConnection, QueryableByName, RunQueryDsl};
+use serde_json::value::RawValue;
+
+use crate::bounds::quote_identifiers;
+use crate::{
+    CsvSink, DynamicColumn, DynamicTable, DynamicTableError, JsonLinesSink, Progress, SerialCursor,
+    StreamEnd,
+};
+
+/// Streams a Postgres table to stdout or a file, as CSV or JSON Lines,
+/// without writing a binary for it.
+///
+/// Run as `diesel-streamer users --cursor id --format json-lines`. The
+/// table's rows are streamed in chunks of `--chunk-size` cursor values,
+/// reporting progress on stderr after every chunk.
+///
+/// With `--checkpoint`, the cursor where the next chunk starts is written to
+/// that file after every chunk. Running the same command again resumes from
+/// it, appending to `--output` instead of overwriting it. The chunk in
+/// flight when the previous run was interrupted may be written twice.
+#[derive(Debug, Clone, PartialEq, Eq, Parser)]
+#[command(name = "diesel-streamer", version, about)]
+#[non_exhaustive]
+pub struct Args {
+    /// The table to stream, optionally qualified by its schema, e.g.
+    /// `public.users`.
+    pub table: String,
+    /// The Postgres database to stream from.
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: String,
+    /// The table's serial column, a `SMALLINT`, `INTEGER` or `BIGINT`.
+    #[arg(long, default_value = "id")]
+    pub cursor: String,
+    /// How many cursor values each chunk covers.
+    #[arg(long, default_value = "100000", value_parser = clap::value_parser!(i64).range(1..))]
+    pub chunk_size: i64,
+    /// The cursor value to start from. Defaults to its minimum.
+    #[arg(long)]
+    pub from: Option<i64>,
+    /// The cursor value to stop at, inclusive. Defaults to its maximum.
+    #[arg(long)]
+    pub to: Option<i64>,
+    /// How the rows are written out, as `csv` or `json-lines`.
+    #[arg(long, value_enum, default_value = "csv")]
+    pub format: Format,
+    /// The file to write the rows to. Defaults to stdout.
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// The file to record the cursor in after every chunk, and to resume
+    /// from.
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+    /// Does not report progress on stderr.
+    #[arg(long, short)]
+    pub quiet: bool,
+}
+
+/// How the rows are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A header row made of the column names, then a record per row, with
+    /// every value in its Postgres text form.
+    Csv,
+    /// A JSON object per row and per line, as built by `row_to_json`.
+    JsonLines,
+}
+
+impl Args {
+    /// The cursor recorded in the checkpoint file, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// When the checkpoint file cannot be read or does not hold a cursor.
+    pub fn checkpointed_cursor(&self) -> io::Result<Option<i64>> {
+        let Some(checkpoint) = &self.checkpoint else {
+            return Ok(None);
+        };
+
+        match fs::read_to_string(checkpoint) {
+            Ok(cursor) if cursor.trim().is_empty() => Ok(None),
+            Ok(cursor) => cursor.trim().parse().map(Some).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} does not hold a cursor", checkpoint.display()),
+                )
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Connects to `args.database_url` and streams the table to `args.output`,
+/// or stdout, reporting progress on stderr.
+///
+/// # Errors
+///
+/// When connecting, querying, writing the rows or the checkpoint fails.
+pub fn run(args: &Args) -> Result<StreamEnd<i64>, Box<dyn Error>> {
+    let mut conn = PgConnection::establish(&args.database_url)?;
+    let checkpointed_cursor = args.checkpointed_cursor()?;
+    let resuming = checkpointed_cursor.is_some();
+
+    match &args.output {
+        Some(output) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(resuming)
+                .truncate(!resuming)
+                .open(output)?;
+
+            stream(
+                &mut conn,
+                args,
+                checkpointed_cursor,
+                BufWriter::new(file),
+                io::stderr(),
+            )
+        }
+        None => stream(
+            &mut conn,
+            args,
+            checkpointed_cursor,
+            io::stdout().lock(),
+            io::stderr(),
+        ),
+    }
+}
+
+/// Streams the table through `conn` to `output`, reporting progress to
+/// `progress`, as `run` does. Resumes from `checkpointed_cursor`, as read by
+/// [`Args::checkpointed_cursor`], when there is one.
+///
+/// # Errors
+///
+/// When querying, writing the rows or the checkpoint fails.
+pub fn stream<W: Write, P: Write>(
+    conn: &mut PgConnection,
+    args: &Args,
+    checkpointed_cursor: Option<i64>,
+    output: W,
+    mut progress: P,
+) -> Result<StreamEnd<i64>, Box<dyn Error>> {
+    let columns = DynamicTable::columns_query(&args.table)
+        .load::<DynamicColumn>(conn)
+        .map_err(DynamicTableError::Query)?;
+    let dynamic_table = DynamicTable::new(&args.table, columns, Some(&args.cursor))?;
+
+    let table = quote_identifiers(&[dynamic_table.schema.clone(), dynamic_table.name.clone()]);
+    let cursor = format!(
+        "t.{}",
+        quote_identifiers(std::slice::from_ref(&dynamic_table.cursor))
+    );
+    let columns = dynamic_table
+        .columns
+        .into_iter()
+        .map(|column| column.name)
+        .collect::<Vec<_>>();
+
+    let bounds = diesel::sql_query(format!(
+        "SELECT MIN({cursor})::BIGINT AS lower, MAX({cursor})::BIGINT AS upper FROM {table} AS t",
+    ))
+    .get_result::<CursorBounds>(conn)?;
+
+    let from = checkpointed_cursor.or(args.from).or(bounds.lower);
+    let to = args.to.or(bounds.upper);
+
+    let mut sink = match args.format {
+        Format::Csv => {
+            let mut sink = CsvSink::new(output);
+
+            if checkpointed_cursor.is_none() {
+                sink.write_chunk(&[&columns])?;
+            }
+
+            Sink::Csv(Box::new(sink))
+        }
+        Format::JsonLines => Sink::JsonLines(JsonLinesSink::new(output)),
+    };
+
+    let (mut from, to) = match (from, to) {
+        (Some(from), Some(to)) if from <= to => (from, to),
+        _ => {
+            sink.flush()?;
+
+            return Ok(StreamEnd::Completed {
+                cursor: checkpointed_cursor,
+            });
+        }
+    };
+
+    let chunk_query = chunk_query(args.format, &table, &cursor, &columns);
+
+    let mut stream_progress = Progress::start(from, to);
+
+    loop {
+        let chunk_to = std::cmp::min(SerialCursor::chunk_end(from, args.chunk_size), to);
+        let query = diesel::sql_query(&chunk_query)
+            .bind::<BigInt, _>(from)
+            .bind::<BigInt, _>(chunk_to);
+
+        let rows = match &mut sink {
+            Sink::Csv(sink) => {
+                let rows = query.load::<CsvRow>(conn)?;
+                let records = rows.iter().map(|row| &row.fields).collect::<Vec<_>>();
+
+                sink.write_chunk(&records)?;
+                sink.flush()?;
+
+                rows.len()
+            }
+            Sink::JsonLines(sink) => {
+                let rows = query
+                    .load::<JsonRow>(conn)?
+                    .into_iter()
+                    .map(|row| RawValue::from_string(row.fields))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                sink.write_chunk(&rows)?;
+
+                rows.len()
+            }
+        };
+
+        let next_cursor = SerialCursor::checked_next(chunk_to);
+        let next = next_cursor.filter(|&next| next <= to);
+        // Saturates at the cursor type's maximum, where the stream ends anyway,
+        // so resuming from the checkpoint writes at most that row again
+        let cursor = next_cursor.unwrap_or(chunk_to);
+
+        if let Some(checkpoint) = &args.checkpoint {
+            write_checkpoint(checkpoint, cursor)?;
+        }
+
+        stream_progress.record_chunk(cursor, rows);
+
+        if !args.quiet {
+            report_progress(&mut progress, &stream_progress, next.is_some())?;
+        }
+
+        match next {
+            Some(next) => from = next,
+            None => {
+                return Ok(StreamEnd::Completed {
+                    cursor: next_cursor,
+                })
+            }
+        }
+    }
+}
+
+enum Sink<W: Write> {
+    Csv(Box<CsvSink<W>>),
+    JsonLines(JsonLinesSink<W>),
+}
+
+impl<W: Write> Sink<W> {
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Csv(sink) => sink.flush(),
+            // Flushed after every chunk already
+            Sink::JsonLines(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct CursorBounds {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    lower: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    upper: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+struct CsvRow {
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    fields: Vec<Option<String>>,
+}
+
+#[derive(QueryableByName)]
+struct JsonRow {
+    #[diesel(sql_type = Text)]
+    fields: String,
+}
+
+/// The query of a chunk of `table`, binding the chunk's bounds as `$1` and
+/// `$2`. Each row holds its fields, as text for CSV or as a JSON object.
+fn chunk_query(format: Format, table: &str, cursor: &str, columns: &[String]) -> String {
+    match format {
+        Format::Csv => format!(
+            "SELECT ARRAY[{fields}] AS fields FROM {table} AS t WHERE {cursor} BETWEEN $1 AND $2 ORDER BY {cursor}",
+            fields = columns
+                .iter()
+                .map(|column| format!("t.{}::TEXT", quote_identifiers(std::slice::from_ref(column))))
+                .collect::<Vec<_>>()
+                .join(", "),
+            table = table,
+            cursor = cursor,
+        ),
+        Format::JsonLines => format!(
+            "SELECT row_to_json(t)::TEXT AS fields FROM {table} AS t WHERE {cursor} BETWEEN $1 AND $2 ORDER BY {cursor}",
+        ),
+    }
+}
+
+// Written aside and renamed over the checkpoint, so that an interrupted
+// write never leaves a truncated cursor behind.
+fn write_checkpoint(checkpoint: &Path, cursor: i64) -> io::Result<()> {
+    let mut pending = checkpoint.as_os_str().to_owned();
+    pending.push(".pending");
+
+    fs::write(&pending, cursor.to_string())?;
+    fs::rename(&pending, checkpoint)
+}
+
+fn report_progress<P: Write>(
+    progress: &mut P,
+    stream_progress: &Progress<i64>,
+    remaining: bool,
+) -> io::Result<()> {
+    write!(
+        progress,
+        "{} rows in {} chunks, cursor at {} of {} ({:.1}%)",
+        stream_progress.rows,
+        stream_progress.chunks,
+        stream_progress.cursor,
+        stream_progress.to,
+        stream_progress.percent_complete(),
+    )?;
+
+    match stream_progress.eta().filter(|_| remaining) {
+        Some(eta) => writeln!(progress, ", {}s left", eta.as_secs()),
+        None => writeln!(progress),
+    }
+}
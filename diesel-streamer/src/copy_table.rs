@@ -0,0 +1,527 @@
+/// Copies a serial table into another table chunk by chunk: each chunk is
+/// loaded from the source, mapped row by row into an `Insertable` of the
+/// target table and inserted with as few `INSERT`s as Postgres' limit of 65535
+/// bind parameters allows. Without
+/// [`transaction_per_chunk`](StreamOptions::transaction_per_chunk), a chunk
+/// whose rows need more than one `INSERT` may fail after some of them.
+///
+/// Takes the source table (or a query on it), its serial field, a connection,
+/// the target table after `into =` and the mapping function last. In
+/// between, any of these may be given, in this order:
+///
+/// - `target_conn = ...` to insert through another connection, e.g. into
+///   another database
+/// - `options = ...` to configure the stream through [`StreamOptions`],
+///   such as resuming `from` a previous [`BatchEnd`]'s cursor, reporting
+///   progress or only copying the rows of a [`shard`](StreamOptions::shard)
+/// - `on_conflict = ...` with the conflict target, to turn the insert into
+///   `ON CONFLICT (...) DO NOTHING`
+/// - `do_update = ...` after `on_conflict`, with the changeset turning it into
+///   `ON CONFLICT (...) DO UPDATE SET ...`
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of inserted
+/// rows, or a [`BatchError`] with the chunk that could not be loaded or
+/// inserted.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel::upsert::excluded;
+/// use diesel_streamer::{copy_table, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::users::dsl::{users, id};
+///     use crate::schema::user_profiles::dsl::{user_profiles, user_id, display_name};
+///
+///     let mut conn = pool.get().await.unwrap();
+///
+///     copy_table!(users, id, conn, into = user_profiles, |user: User| NewUserProfile::from(user)).unwrap();
+///
+///     // into another database, overwriting existing profiles
+///     let mut target_conn = target_pool.get().await.unwrap();
+///     let batch_end = copy_table!(
+///         users,
+///         id,
+///         conn,
+///         into = user_profiles,
+///         target_conn = target_conn,
+///         options = StreamOptions::new().chunk_size(1000),
+///         on_conflict = user_id,
+///         do_update = display_name.eq(excluded(display_name)),
+///         |user: User| NewUserProfile::from(user)
+///     )
+///     .unwrap();
+///
+///     println!("copied {} users", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_copy_table {
+    (@args $copy:tt [$default_target_conn:expr] $options:tt $on_conflict:tt target_conn = $target_conn:expr , $($rest:tt)+) => {
+        diesel_streamer::__async_copy_table!(@args $copy [$target_conn] $options $on_conflict $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt [$default_options:expr] $on_conflict:tt options = $options:expr , $($rest:tt)+) => {
+        diesel_streamer::__async_copy_table!(@args $copy $target_conn [$options] $on_conflict $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt $options:tt [] on_conflict = $conflict_target:expr , $($rest:tt)+) => {
+        diesel_streamer::__async_copy_table!(@args $copy $target_conn $options [$conflict_target, do_nothing] $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt $options:tt [$conflict_target:expr, do_nothing] do_update = $changeset:expr , $($rest:tt)+) => {
+        diesel_streamer::__async_copy_table!(@args $copy $target_conn $options [$conflict_target, do_update = $changeset] $($rest)+)
+    };
+
+    (@args [$source:expr, $cursor_field:expr, $conn:expr, $target:expr] [$target_conn:expr] [$options:expr] $on_conflict:tt $map:expr) => {{
+        use diesel::prelude::*;
+        use diesel_async::scoped_futures::ScopedFutureExt;
+        use diesel_async::{AsyncConnection, RunQueryDsl};
+
+        fn backend_of<C: diesel_async::AsyncConnection>(
+            _conn: &C,
+        ) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(.await;
+            $source,
+            $cursor_field,
+            $conn,
+            options,
+            500,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let copied_count = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let copied_rows = match shard_filter {
+                        Some(shard_filter) => {
+                            let chunk_query = $source
+                                .filter($cursor_field.eq_any(from..=chunk_to))
+                                .filter(shard_filter);
+                            chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                            chunk_query.load($conn).await
+                        }
+                        None => {
+                            let chunk_query = $source.filter($cursor_field.eq_any(from..=chunk_to));
+                            chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                            chunk_query.load($conn).await
+                        }
+                    };
+                    chunk.record_query(&copied_rows);
+
+                    let copied_count = match copied_rows {
+                        Ok(copied_rows) if copied_rows.is_empty() => Ok(0),
+                        Ok(copied_rows) => {
+                            let copied_count = copied_rows.len();
+                            let insert_queries = diesel_streamer::__async_copy_table!(
+                                @insert_queries $target, $on_conflict, copied_rows.into_iter().map($map)
+                            );
+                            for insert_query in &insert_queries {
+                                chunk.trace_query(insert_query, backend_of(&*$target_conn));
+                            }
+
+                            let inserted_rows = if options.transaction_per_chunk {
+                                let lock_timeout_statement = options.lock_timeout_statement();
+
+                                $target_conn
+                                    .transaction::<_, diesel::result::Error, _>(|conn| {
+                                        async move {
+                                            if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                                diesel::sql_query(lock_timeout_statement)
+                                                    .execute(conn)
+                                                    .await?;
+                                            }
+
+                                            let mut inserted_rows = 0;
+                                            for insert_query in insert_queries {
+                                                inserted_rows += insert_query.execute(conn).await?;
+                                            }
+
+                                            Ok(inserted_rows)
+                                        }
+                                        .scope_boxed()
+                                    })
+                                    .await
+                            } else {
+                                async {
+                                    let mut inserted_rows = 0;
+                                    for insert_query in insert_queries {
+                                        inserted_rows += insert_query.execute($target_conn).await?;
+                                    }
+
+                                    Ok::<_, diesel::result::Error>(inserted_rows)
+                                }
+                                .await
+                            };
+                            chunk.record_insert(&inserted_rows);
+
+                            inserted_rows.map(|inserted_rows| {
+                                affected_rows += inserted_rows;
+
+                                copied_count
+                            })
+                        }
+                        Err(error) => Err(error),
+                    };
+
+                    match copied_count {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        copied_count => break copied_count,
+                    }
+                };
+                chunk.record_processor();
+
+                match copied_count {
+                    Ok(copied_count) => std::ops::ControlFlow::Continue(copied_count),
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    (@insert_queries $target:expr, $on_conflict:tt, $new_rows:expr) => {{
+        let batch_size = diesel_streamer::__insert_batch_size($target);
+        let mut new_rows = $new_rows;
+
+        std::iter::from_fn(|| {
+            let batch: Vec<_> = new_rows.by_ref().take(batch_size).collect();
+
+            (!batch.is_empty()).then(|| {
+                diesel_streamer::__async_copy_table!(
+                    @on_conflict $on_conflict diesel::insert_into($target).values(batch)
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+    }};
+
+    (@on_conflict [] $insert_query:expr) => {
+        $insert_query
+    };
+
+    (@on_conflict [$conflict_target:expr, do_nothing] $insert_query:expr) => {
+        $insert_query.on_conflict($conflict_target).do_nothing()
+    };
+
+    (@on_conflict [$conflict_target:expr, do_update = $changeset:expr] $insert_query:expr) => {
+        $insert_query
+            .on_conflict($conflict_target)
+            .do_update()
+            .set($changeset)
+    };
+
+    ( $source:expr , $cursor_field:expr , $conn:expr , into = $target:expr , $($args:tt)+ ) => {
+        diesel_streamer::__async_copy_table!(
+            @args [$source, $cursor_field, $conn, $target]
+            [$conn]
+            [diesel_streamer::StreamOptions::new()]
+            []
+            $($args)+
+        )
+    };
+}
+
+/// Copies a serial table into another table chunk by chunk: each chunk is
+/// loaded from the source, mapped row by row into an `Insertable` of the
+/// target table and inserted with as few `INSERT`s as Postgres' limit of 65535
+/// bind parameters allows. Without
+/// [`transaction_per_chunk`](StreamOptions::transaction_per_chunk), a chunk
+/// whose rows need more than one `INSERT` may fail after some of them.
+///
+/// Takes the source table (or a query on it), its serial field, a connection,
+/// the target table after `into =` and the mapping function last. In
+/// between, any of these may be given, in this order:
+///
+/// - `target_conn = ...` to insert through another connection, e.g. into
+///   another database
+/// - `options = ...` to configure the stream through [`StreamOptions`],
+///   such as resuming `from` a previous [`BatchEnd`]'s cursor, reporting
+///   progress or only copying the rows of a [`shard`](StreamOptions::shard)
+/// - `on_conflict = ...` with the conflict target, to turn the insert into
+///   `ON CONFLICT (...) DO NOTHING`
+/// - `do_update = ...` after `on_conflict`, with the changeset turning it into
+///   `ON CONFLICT (...) DO UPDATE SET ...`
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of inserted
+/// rows, or a [`BatchError`] with the chunk that could not be loaded or
+/// inserted.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel::upsert::excluded;
+/// use diesel_streamer::{copy_table, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::users::dsl::{users, id};
+///     use crate::schema::user_profiles::dsl::{user_profiles, user_id, display_name};
+///
+///     let mut conn = pool.get().unwrap();
+///
+///     copy_table!(users, id, conn, into = user_profiles, |user: User| NewUserProfile::from(user)).unwrap();
+///
+///     // into another database, overwriting existing profiles
+///     let mut target_conn = target_pool.get().unwrap();
+///     let batch_end = copy_table!(
+///         users,
+///         id,
+///         conn,
+///         into = user_profiles,
+///         target_conn = target_conn,
+///         options = StreamOptions::new().chunk_size(1000),
+///         on_conflict = user_id,
+///         do_update = display_name.eq(excluded(display_name)),
+///         |user: User| NewUserProfile::from(user)
+///     )
+///     .unwrap();
+///
+///     println!("copied {} users", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_copy_table {
+    (@args $copy:tt [$default_target_conn:expr] $options:tt $on_conflict:tt target_conn = $target_conn:expr , $($rest:tt)+) => {
+        diesel_streamer::__sync_copy_table!(@args $copy [$target_conn] $options $on_conflict $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt [$default_options:expr] $on_conflict:tt options = $options:expr , $($rest:tt)+) => {
+        diesel_streamer::__sync_copy_table!(@args $copy $target_conn [$options] $on_conflict $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt $options:tt [] on_conflict = $conflict_target:expr , $($rest:tt)+) => {
+        diesel_streamer::__sync_copy_table!(@args $copy $target_conn $options [$conflict_target, do_nothing] $($rest)+)
+    };
+
+    (@args $copy:tt $target_conn:tt $options:tt [$conflict_target:expr, do_nothing] do_update = $changeset:expr , $($rest:tt)+) => {
+        diesel_streamer::__sync_copy_table!(@args $copy $target_conn $options [$conflict_target, do_update = $changeset] $($rest)+)
+    };
+
+    (@args [$source:expr, $cursor_field:expr, $conn:expr, $target:expr] [$target_conn:expr] [$options:expr] $on_conflict:tt $map:expr) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(;
+            $source,
+            $cursor_field,
+            $conn,
+            options,
+            100000,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let copied_count = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let copied_rows = match shard_filter {
+                        Some(shard_filter) => {
+                            let chunk_query = $source
+                                .filter($cursor_field.eq_any(from..=chunk_to))
+                                .filter(shard_filter);
+                            chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                            chunk_query.load($conn)
+                        }
+                        None => {
+                            let chunk_query = $source.filter($cursor_field.eq_any(from..=chunk_to));
+                            chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                            chunk_query.load($conn)
+                        }
+                    };
+                    chunk.record_query(&copied_rows);
+
+                    let copied_count = match copied_rows {
+                        Ok(copied_rows) if copied_rows.is_empty() => Ok(0),
+                        Ok(copied_rows) => {
+                            let copied_count = copied_rows.len();
+                            let insert_queries = diesel_streamer::__sync_copy_table!(
+                                @insert_queries $target, $on_conflict, copied_rows.into_iter().map($map)
+                            );
+                            for insert_query in &insert_queries {
+                                chunk.trace_query(insert_query, backend_of(&*$target_conn));
+                            }
+
+                            let inserted_rows = if options.transaction_per_chunk {
+                                let lock_timeout_statement = options.lock_timeout_statement();
+
+                                $target_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                                    if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                        diesel::sql_query(lock_timeout_statement).execute(conn)?;
+                                    }
+
+                                    insert_queries
+                                        .into_iter()
+                                        .try_fold(0, |inserted_rows, insert_query| {
+                                            Ok(inserted_rows + insert_query.execute(conn)?)
+                                        })
+                                })
+                            } else {
+                                insert_queries
+                                    .into_iter()
+                                    .try_fold(0, |inserted_rows, insert_query| {
+                                        Ok::<_, diesel::result::Error>(
+                                            inserted_rows + insert_query.execute($target_conn)?,
+                                        )
+                                    })
+                            };
+                            chunk.record_insert(&inserted_rows);
+
+                            inserted_rows.map(|inserted_rows| {
+                                affected_rows += inserted_rows;
+
+                                copied_count
+                            })
+                        }
+                        Err(error) => Err(error),
+                    };
+
+                    match copied_count {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        copied_count => break copied_count,
+                    }
+                };
+                chunk.record_processor();
+
+                match copied_count {
+                    Ok(copied_count) => std::ops::ControlFlow::Continue(copied_count),
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    (@insert_queries $target:expr, $on_conflict:tt, $new_rows:expr) => {{
+        let batch_size = diesel_streamer::__insert_batch_size($target);
+        let mut new_rows = $new_rows;
+
+        std::iter::from_fn(|| {
+            let batch: Vec<_> = new_rows.by_ref().take(batch_size).collect();
+
+            (!batch.is_empty()).then(|| {
+                diesel_streamer::__sync_copy_table!(
+                    @on_conflict $on_conflict diesel::insert_into($target).values(batch)
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+    }};
+
+    (@on_conflict [] $insert_query:expr) => {
+        $insert_query
+    };
+
+    (@on_conflict [$conflict_target:expr, do_nothing] $insert_query:expr) => {
+        $insert_query.on_conflict($conflict_target).do_nothing()
+    };
+
+    (@on_conflict [$conflict_target:expr, do_update = $changeset:expr] $insert_query:expr) => {
+        $insert_query
+            .on_conflict($conflict_target)
+            .do_update()
+            .set($changeset)
+    };
+
+    ( $source:expr , $cursor_field:expr , $conn:expr , into = $target:expr , $($args:tt)+ ) => {
+        diesel_streamer::__sync_copy_table!(
+            @args [$source, $cursor_field, $conn, $target]
+            [$conn]
+            [diesel_streamer::StreamOptions::new()]
+            []
+            $($args)+
+        )
+    };
+}
+
+/// The most bind parameters a single statement may hold in Postgres.
+const MAX_BIND_PARAMS: usize = 65535;
+
+/// The number of columns of a table, read off its `AllColumns` tuple.
+#[doc(hidden)]
+pub trait __ColumnCount {
+    const COUNT: usize;
+}
+
+// Implemented for the tuples up to the 128 columns of Diesel's
+// `128-column-tables` feature, each call peeling the columns after `;` one by
+// one to stay within the macro recursion limit.
+macro_rules! column_count_impls {
+    ($($prefix:ident),* ;) => {};
+    ($($prefix:ident),* ; $first:ident $(, $rest:ident)*) => {
+        impl<$($prefix,)* $first $(, $rest)*> __ColumnCount for ($($prefix,)* $first, $($rest,)*) {
+            const COUNT: usize =
+                1 $(+ column_count_impls!(@one $prefix))* $(+ column_count_impls!(@one $rest))*;
+        }
+
+        column_count_impls!($($prefix),* ; $($rest),*);
+    };
+    (@one $column:ident) => {
+        1
+    };
+}
+
+column_count_impls!(
+    C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15, C16, C17, C18, C19,
+    C20, C21, C22, C23, C24, C25, C26, C27, C28, C29, C30, C31, C32, C33, C34, C35, C36, C37,
+    C38, C39, C40, C41, C42, C43, C44, C45, C46, C47, C48, C49, C50, C51, C52, C53, C54, C55,
+    C56, C57, C58, C59, C60, C61, C62, C63;
+    C64, C65, C66, C67, C68, C69, C70, C71, C72, C73, C74, C75, C76, C77, C78, C79, C80, C81,
+    C82, C83, C84, C85, C86, C87, C88, C89, C90, C91, C92, C93, C94, C95, C96, C97, C98, C99,
+    C100, C101, C102, C103, C104, C105, C106, C107, C108, C109, C110, C111, C112, C113, C114,
+    C115, C116, C117, C118, C119, C120, C121, C122, C123, C124, C125, C126, C127
+);
+column_count_impls!(;
+    C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15, C16, C17, C18, C19,
+    C20, C21, C22, C23, C24, C25, C26, C27, C28, C29, C30, C31, C32, C33, C34, C35, C36, C37,
+    C38, C39, C40, C41, C42, C43, C44, C45, C46, C47, C48, C49, C50, C51, C52, C53, C54, C55,
+    C56, C57, C58, C59, C60, C61, C62, C63
+);
+
+/// How many rows `copy_table!` inserts with one `INSERT` into `table`, so
+/// that a row binding every column stays within Postgres' bind parameter
+/// limit.
+#[doc(hidden)]
+pub fn __insert_batch_size<T>(_table: T) -> usize
+where
+    T: diesel::Table,
+    T::AllColumns: __ColumnCount,
+{
+    MAX_BIND_PARAMS / <T::AllColumns as __ColumnCount>::COUNT
+}
@@ -0,0 +1,220 @@
+use std::io::{self, Write};
+
+use csv::{Writer, WriterBuilder};
+use serde::Serialize;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+pub use csv::QuoteStyle;
+
+/// Writes the chunks of a stream as CSV to any [`Write`].
+///
+/// Rows may be any `T: Serialize`. When they are structs, a header row made
+/// of their field names is written before the first row.
+///
+/// # Examples
+///
+/// ```
+/// use diesel_streamer::CsvSinkBuilder;
+///
+/// let mut sink = CsvSinkBuilder::new().delimiter(b';').build(vec![]);
+///
+/// sink.write_chunk(&[(1, "Ada Lovelace"), (2, "Alan Turing")]).unwrap();
+///
+/// let csv = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+/// assert_eq!(csv, "1;Ada Lovelace\n2;Alan Turing\n");
+/// ```
+///
+/// Inside `stream_serial_table!`:
+///
+/// ```ignore
+/// let mut sink = CsvSink::new(File::create("users.csv")?);
+///
+/// stream_serial_table!(users, id, conn, |users: Vec<User>| sink.write_chunk(&users));
+/// ```
+#[derive(Debug)]
+pub struct CsvSink<W: Write> {
+    writer: Writer<W>,
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Writes comma-separated rows, quoting fields only when necessary.
+    pub fn new(writer: W) -> Self {
+        CsvSinkBuilder::new().build(writer)
+    }
+
+    /// Writes a chunk of rows. The header row is written along with the
+    /// first row.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row cannot be serialized as a CSV record, or when
+    /// writing fails.
+    pub fn write_chunk<T: Serialize>(&mut self, rows: &[T]) -> Result<(), csv::Error> {
+        for row in rows {
+            self.writer.serialize(row)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the rows written so far to the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails when writing to the underlying writer fails.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flushes the remaining rows and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails when writing to the underlying writer fails.
+    pub fn into_inner(self) -> Result<W, csv::Error> {
+        self.writer
+            .into_inner()
+            .map_err(|error| error.into_error().into())
+    }
+}
+
+/// Writes the chunks of a stream as CSV to any tokio `AsyncWrite`, e.g. the
+/// chunks of a `SerialTableStream`.
+///
+/// Every chunk is formatted in memory before being written out, so the
+/// writer is only ever awaited once per chunk.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut sink = AsyncCsvSink::new(tokio::fs::File::create("users.csv").await?);
+///
+/// while let Some(users) = stream.next().await {
+///     sink.write_chunk(&users).await?;
+/// }
+///
+/// sink.flush().await?;
+/// ```
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct AsyncCsvSink<W> {
+    writer: W,
+    builder: WriterBuilder,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "async")]
+impl<W: AsyncWrite + Unpin> AsyncCsvSink<W> {
+    /// Writes comma-separated rows, quoting fields only when necessary.
+    pub fn new(writer: W) -> Self {
+        CsvSinkBuilder::new().build_async(writer)
+    }
+
+    /// Writes a chunk of rows. The header row is written along with the
+    /// first row.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row cannot be serialized as a CSV record, or when
+    /// writing fails.
+    pub async fn write_chunk<T: Serialize>(&mut self, rows: &[T]) -> Result<(), csv::Error> {
+        self.buffer.clear();
+
+        let mut buffer = self.builder.from_writer(&mut self.buffer);
+
+        for row in rows {
+            buffer.serialize(row)?;
+        }
+
+        buffer.flush()?;
+        drop(buffer);
+
+        if !rows.is_empty() {
+            // The header row went out with this chunk.
+            self.builder.has_headers(false);
+        }
+
+        self.writer.write_all(&self.buffer).await?;
+
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails when flushing the underlying writer fails.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush().await
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Configures the CSV format of a [`CsvSink`] or an [`AsyncCsvSink`].
+#[derive(Debug)]
+pub struct CsvSinkBuilder {
+    builder: WriterBuilder,
+}
+
+impl CsvSinkBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        CsvSinkBuilder {
+            builder: WriterBuilder::new(),
+        }
+    }
+
+    /// Separates fields with `delimiter` instead of `,`.
+    #[must_use]
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.builder.delimiter(delimiter);
+        self
+    }
+
+    /// Quotes fields with `quote` instead of `"`.
+    #[must_use]
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.builder.quote(quote);
+        self
+    }
+
+    /// Decides which fields get quoted. Defaults to [`QuoteStyle::Necessary`].
+    #[must_use]
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.builder.quote_style(quote_style);
+        self
+    }
+
+    /// Whether to write a header row for struct rows. Defaults to `true`.
+    #[must_use]
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.builder.has_headers(has_headers);
+        self
+    }
+
+    pub fn build<W: Write>(self, writer: W) -> CsvSink<W> {
+        CsvSink {
+            writer: self.builder.from_writer(writer),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub fn build_async<W: AsyncWrite + Unpin>(self, writer: W) -> AsyncCsvSink<W> {
+        AsyncCsvSink {
+            writer,
+            builder: self.builder,
+            buffer: vec![],
+        }
+    }
+}
+
+impl Default for CsvSinkBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
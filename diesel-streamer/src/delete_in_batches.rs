@@ -0,0 +1,495 @@
+/// Deletes rows of a serial table in chunks, issuing one
+/// `DELETE ... WHERE cursor >= from AND cursor <= chunk_to` per chunk, so
+/// purging a big table neither locks it for long nor floods the WAL at once.
+///
+/// Takes the table (or a filtered query on it), its serial field and a
+/// connection. When given a processor, each chunk is deleted with
+/// `RETURNING` and the processor receives the deleted rows, e.g. to archive
+/// them, within the chunk's transaction. It may return anything implementing
+/// [`StreamControl`] to stop the stream, in which case the chunk's `DELETE`
+/// is rolled back, so no row is gone before the processor is done with it.
+///
+/// Defaults are the same as `stream_serial_table!`'s. Pass `options = ...`
+/// to configure the stream through [`StreamOptions`], e.g. to
+/// [`pause`](StreamOptions::pause) between chunks or to run each chunk in
+/// its own transaction with a [`lock_timeout`](StreamOptions::lock_timeout)
+/// or to only delete the rows of a [`shard`](StreamOptions::shard).
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of deleted
+/// rows, or a [`BatchError`] with the chunk whose `DELETE` failed, e.g. on a
+/// lock timeout.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{delete_in_batches, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::events::dsl::{events, id, created_at};
+///
+///     let mut conn = pool.get().await.unwrap();
+///
+///     let batch_end = delete_in_batches!(events.filter(created_at.lt(last_year)), id, conn).unwrap();
+///
+///     // archiving the deleted rows, with a breather between chunks
+///     let options = StreamOptions::new()
+///         .chunk_size(1000)
+///         .pause(Duration::from_millis(100));
+///     let batch_end = delete_in_batches!(events.filter(created_at.lt(last_year)), id, conn, options = options, |deleted_events: Vec<Event>| async {
+///         archive(deleted_events).await
+///     })
+///     .unwrap();
+///
+///     println!("deleted {} events", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_delete_in_batches {
+    ( $target:expr , $cursor_field:expr , $conn:expr , options = $options:expr , $processor:expr ) => {{
+        use diesel::prelude::*;
+        use diesel_async::scoped_futures::ScopedFutureExt;
+        use diesel_async::{AsyncConnection, RunQueryDsl};
+
+        fn backend_of<C: diesel_async::AsyncConnection>(
+            _conn: &C,
+        ) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+        let mut processor = $processor;
+
+        let stream_end = diesel_streamer::__stream_chunks!(.await;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            500,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let deleted_count = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let delete_query = diesel::delete(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    );
+                    // Boxed, so both arms share a type
+                    let delete_query = match shard_filter {
+                        Some(shard_filter) => delete_query.filter(shard_filter).into_boxed(),
+                        None => delete_query.into_boxed(),
+                    };
+                    chunk.trace_query(&delete_query, backend_of(&*$conn));
+
+                    let lock_timeout_statement = options.lock_timeout_statement();
+                    let processor = &mut processor;
+                    let chunk_instrument = &mut chunk;
+
+                    let deleted_count = $conn
+                        .transaction::<_, diesel_streamer::__ChunkError<_>, _>(|conn| {
+                            async move {
+                                if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                    diesel::sql_query(lock_timeout_statement)
+                                        .execute(conn)
+                                        .await?;
+                                }
+
+                                let deleted_rows = delete_query.get_results(conn).await;
+                                chunk_instrument.record_query(&deleted_rows);
+
+                                let deleted_rows = deleted_rows?;
+                                let deleted_count = deleted_rows.len();
+
+                                let stream_control = chunk_instrument.instrument(processor(deleted_rows)).await;
+                                chunk_instrument.record_processor();
+                                let failed = diesel_streamer::StreamControl::is_error(&stream_control);
+
+                                // Rolls the chunk back, so its rows are only gone
+                                // once the processor is done with them
+                                match diesel_streamer::StreamControl::into_control_flow(stream_control) {
+                                    std::ops::ControlFlow::Continue(()) => Ok(deleted_count),
+                                    std::ops::ControlFlow::Break(reason) => {
+                                        chunk_instrument.record_break(failed);
+
+                                        Err(diesel_streamer::__ChunkError::Stopped(reason))
+                                    }
+                                }
+                            }
+                            .scope_boxed()
+                        })
+                        .await;
+
+                    match deleted_count {
+                        Err(diesel_streamer::__ChunkError::Query(_)) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        deleted_count => break deleted_count,
+                    }
+                };
+
+                match deleted_count {
+                    Ok(deleted_count) => {
+                        affected_rows += deleted_count;
+
+                        std::ops::ControlFlow::Continue(deleted_count)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((chunk_to, error)),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , options = $options:expr ) => {{
+        use diesel::prelude::*;
+        use diesel_async::scoped_futures::ScopedFutureExt;
+        use diesel_async::{AsyncConnection, RunQueryDsl};
+
+        fn backend_of<C: diesel_async::AsyncConnection>(
+            _conn: &C,
+        ) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(.await;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            500,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let deleted_rows = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let delete_query = diesel::delete(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    );
+                    // Boxed, so both arms share a type
+                    let delete_query = match shard_filter {
+                        Some(shard_filter) => delete_query.filter(shard_filter).into_boxed(),
+                        None => delete_query.into_boxed(),
+                    };
+                    chunk.trace_query(&delete_query, backend_of(&*$conn));
+
+                    let deleted_rows = if options.transaction_per_chunk {
+                        let lock_timeout_statement = options.lock_timeout_statement();
+
+                        $conn
+                            .transaction::<_, diesel::result::Error, _>(|conn| {
+                                async move {
+                                    if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                        diesel::sql_query(lock_timeout_statement)
+                                            .execute(conn)
+                                            .await?;
+                                    }
+
+                                    delete_query.execute(conn).await
+                                }
+                                .scope_boxed()
+                            })
+                            .await
+                    } else {
+                        delete_query.execute($conn).await
+                    };
+                    chunk.record_execute(&deleted_rows);
+
+                    match deleted_rows {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        deleted_rows => break deleted_rows,
+                    }
+                };
+
+                match deleted_rows {
+                    Ok(deleted_rows) => {
+                        affected_rows += deleted_rows;
+
+                        std::ops::ControlFlow::Continue(deleted_rows)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , $processor:expr ) => {{
+        use diesel_streamer::__async_delete_in_batches as delete_in_batches;
+
+        delete_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new(),
+            $processor
+        )
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr ) => {{
+        use diesel_streamer::__async_delete_in_batches as delete_in_batches;
+
+        delete_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new()
+        )
+    }};
+}
+
+/// Deletes rows of a serial table in chunks, issuing one
+/// `DELETE ... WHERE cursor >= from AND cursor <= chunk_to` per chunk, so
+/// purging a big table neither locks it for long nor floods the WAL at once.
+///
+/// Takes the table (or a filtered query on it), its serial field and a
+/// connection. When given a processor, each chunk is deleted with
+/// `RETURNING` and the processor receives the deleted rows, e.g. to archive
+/// them, within the chunk's transaction. It may return anything implementing
+/// [`StreamControl`] to stop the stream, in which case the chunk's `DELETE`
+/// is rolled back, so no row is gone before the processor is done with it.
+///
+/// Defaults are the same as `stream_serial_table!`'s. Pass `options = ...`
+/// to configure the stream through [`StreamOptions`], e.g. to
+/// [`pause`](StreamOptions::pause) between chunks or to run each chunk in
+/// its own transaction with a [`lock_timeout`](StreamOptions::lock_timeout)
+/// or to only delete the rows of a [`shard`](StreamOptions::shard).
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of deleted
+/// rows, or a [`BatchError`] with the chunk whose `DELETE` failed, e.g. on a
+/// lock timeout.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{delete_in_batches, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::events::dsl::{events, id, created_at};
+///
+///     let mut conn = pool.get().unwrap();
+///
+///     let batch_end = delete_in_batches!(events.filter(created_at.lt(last_year)), id, conn).unwrap();
+///
+///     // archiving the deleted rows, with a breather between chunks
+///     let options = StreamOptions::new()
+///         .chunk_size(1000)
+///         .pause(Duration::from_millis(100));
+///     let batch_end = delete_in_batches!(events.filter(created_at.lt(last_year)), id, conn, options = options, |deleted_events: Vec<Event>| {
+///         archive(deleted_events)
+///     })
+///     .unwrap();
+///
+///     println!("deleted {} events", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_delete_in_batches {
+    ( $target:expr , $cursor_field:expr , $conn:expr , options = $options:expr , $processor:expr ) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+        let mut processor = $processor;
+
+        let stream_end = diesel_streamer::__stream_chunks!(;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            100000,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let deleted_count = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let delete_query = diesel::delete(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    );
+                    // Boxed, so both arms share a type
+                    let delete_query = match shard_filter {
+                        Some(shard_filter) => delete_query.filter(shard_filter).into_boxed(),
+                        None => delete_query.into_boxed(),
+                    };
+                    chunk.trace_query(&delete_query, backend_of(&*$conn));
+
+                    let lock_timeout_statement = options.lock_timeout_statement();
+                    let processor = &mut processor;
+
+                    let deleted_count = $conn
+                        .transaction::<_, diesel_streamer::__ChunkError<_>, _>(|conn| {
+                            if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                diesel::sql_query(lock_timeout_statement).execute(conn)?;
+                            }
+
+                            let deleted_rows = delete_query.get_results(conn);
+                            chunk.record_query(&deleted_rows);
+
+                            let deleted_rows = deleted_rows?;
+                            let deleted_count = deleted_rows.len();
+
+                            let stream_control = chunk.in_scope(|| processor(deleted_rows));
+                            chunk.record_processor();
+                            let failed = diesel_streamer::StreamControl::is_error(&stream_control);
+
+                            // Rolls the chunk back, so its rows are only gone once
+                            // the processor is done with them
+                            match diesel_streamer::StreamControl::into_control_flow(stream_control) {
+                                std::ops::ControlFlow::Continue(()) => Ok(deleted_count),
+                                std::ops::ControlFlow::Break(reason) => {
+                                    chunk.record_break(failed);
+
+                                    Err(diesel_streamer::__ChunkError::Stopped(reason))
+                                }
+                            }
+                        });
+
+                    match deleted_count {
+                        Err(diesel_streamer::__ChunkError::Query(_)) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        deleted_count => break deleted_count,
+                    }
+                };
+
+                match deleted_count {
+                    Ok(deleted_count) => {
+                        affected_rows += deleted_count;
+
+                        std::ops::ControlFlow::Continue(deleted_count)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((chunk_to, error)),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , options = $options:expr ) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            100000,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let deleted_rows = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let delete_query = diesel::delete(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    );
+                    // Boxed, so both arms share a type
+                    let delete_query = match shard_filter {
+                        Some(shard_filter) => delete_query.filter(shard_filter).into_boxed(),
+                        None => delete_query.into_boxed(),
+                    };
+                    chunk.trace_query(&delete_query, backend_of(&*$conn));
+
+                    let deleted_rows = if options.transaction_per_chunk {
+                        let lock_timeout_statement = options.lock_timeout_statement();
+
+                        $conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                            if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                diesel::sql_query(lock_timeout_statement).execute(conn)?;
+                            }
+
+                            delete_query.execute(conn)
+                        })
+                    } else {
+                        delete_query.execute($conn)
+                    };
+                    chunk.record_execute(&deleted_rows);
+
+                    match deleted_rows {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        deleted_rows => break deleted_rows,
+                    }
+                };
+
+                match deleted_rows {
+                    Ok(deleted_rows) => {
+                        affected_rows += deleted_rows;
+
+                        std::ops::ControlFlow::Continue(deleted_rows)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , $processor:expr ) => {{
+        use diesel_streamer::__sync_delete_in_batches as delete_in_batches;
+
+        delete_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new(),
+            $processor
+        )
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr ) => {{
+        use diesel_streamer::__sync_delete_in_batches as delete_in_batches;
+
+        delete_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new()
+        )
+    }};
+}
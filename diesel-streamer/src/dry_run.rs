@@ -0,0 +1,230 @@
+/// Reports what `stream_serial_table!` would do with the same arguments,
+/// without processing any row.
+///
+/// Resolves the bounds the way the stream would, counts the chunks it would
+/// load and, on Postgres, runs `EXPLAIN` on the query loading the first
+/// chunk. Pass `analyze = true` to run `EXPLAIN ANALYZE` instead, which
+/// loads that one chunk. Accepts the same `select = ...` and
+/// `options = ...` as `stream_serial_table!`.
+///
+/// Evaluates to a `Result` holding a [`DryRun`](crate::DryRun), whose
+/// `warnings` flag a plan scanning the table sequentially instead of through
+/// an index on the cursor field, or the error of the `EXPLAIN`. A
+/// [`DryRun`](crate::DryRun) implements `Display` for a readable report.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{dry_run, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::events::dsl::{events, created_at, id};
+///
+///     let mut conn = pool.get().await.unwrap();
+///
+///     let options = StreamOptions::new().chunk_size(1000);
+///     let dry_run = dry_run!(events.filter(created_at.lt(last_year)), id, conn, options = options, analyze = true).unwrap();
+///
+///     println!("{}", dry_run);
+///     assert!(dry_run.warnings.is_empty());
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_dry_run {
+    (@dry_run $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:expr , $analyze:expr) => {{
+        use diesel::prelude::*;
+        use diesel_async::RunQueryDsl;
+
+        fn backend_of<C: diesel_async::AsyncConnection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let options = $options;
+
+        diesel_streamer::__dry_run!(.await;
+            $query,
+            $cursor_field,
+            $conn,
+            [$($select)?],
+            options,
+            $analyze,
+            500
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr $(, select = $select:expr)? $(, options = $options:expr)? $(, analyze = $analyze:expr)? $(,)? ) => {{
+        diesel_streamer::__async_dry_run!(
+            @dry_run $query,
+            $cursor_field,
+            $conn,
+            [$($select)?],
+            diesel_streamer::__first!($($options,)? diesel_streamer::StreamOptions::new()),
+            diesel_streamer::__first!($($analyze,)? false)
+        )
+    }};
+}
+
+/// Reports what `stream_serial_table!` would do with the same arguments,
+/// without processing any row.
+///
+/// Resolves the bounds the way the stream would, counts the chunks it would
+/// load and, on Postgres, runs `EXPLAIN` on the query loading the first
+/// chunk. Pass `analyze = true` to run `EXPLAIN ANALYZE` instead, which
+/// loads that one chunk. Accepts the same `select = ...` and
+/// `options = ...` as `stream_serial_table!`.
+///
+/// Evaluates to a `Result` holding a [`DryRun`](crate::DryRun), whose
+/// `warnings` flag a plan scanning the table sequentially instead of through
+/// an index on the cursor field, or the error of the `EXPLAIN`. A
+/// [`DryRun`](crate::DryRun) implements `Display` for a readable report.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{dry_run, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::events::dsl::{events, created_at, id};
+///
+///     let mut conn = pool.get().unwrap();
+///
+///     let options = StreamOptions::new().chunk_size(1000);
+///     let dry_run = dry_run!(events.filter(created_at.lt(last_year)), id, conn, options = options, analyze = true).unwrap();
+///
+///     println!("{}", dry_run);
+///     assert!(dry_run.warnings.is_empty());
+/// }
+/// ```
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_dry_run {
+    (@dry_run $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:expr , $analyze:expr) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let options = $options;
+
+        diesel_streamer::__dry_run!(;
+            $query,
+            $cursor_field,
+            $conn,
+            [$($select)?],
+            options,
+            $analyze,
+            100000
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr $(, select = $select:expr)? $(, options = $options:expr)? $(, analyze = $analyze:expr)? $(,)? ) => {{
+        diesel_streamer::__sync_dry_run!(
+            @dry_run $query,
+            $cursor_field,
+            $conn,
+            [$($select)?],
+            diesel_streamer::__first!($($options,)? diesel_streamer::StreamOptions::new()),
+            diesel_streamer::__first!($($analyze,)? false)
+        )
+    }};
+}
+
+/// Resolves the bounds and explains the first chunk on behalf of
+/// `dry_run!`.
+///
+/// Takes the same prefix and expects the same items in scope as
+/// `__stream_chunks!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dry_run {
+    ( $(.$await:tt)? ; $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:ident , $analyze:expr , $default_chunk_size:expr ) => {{
+        let instrument =
+            diesel_streamer::instrument::StreamInstrument::start($options.name.as_deref());
+        let chunk_size = $options.chunk_size.unwrap_or($default_chunk_size);
+        let analyze: bool = $analyze;
+
+        let resolved_bounds = diesel_streamer::__resolve_bounds!($(.$await)?;
+            $query,
+            $cursor_field,
+            $conn,
+            $options,
+            instrument
+        );
+
+        let (bounds, sample_chunk, plan) = if let (Some(from), Some(to)) = resolved_bounds {
+            instrument.record_bounds(from, to, chunk_size);
+
+            let chunk_to = std::cmp::min(
+                match $options.partition_end(from) {
+                    // Past `from`, so never the type's minimum
+                    Some(partition_end) => {
+                        diesel_streamer::SerialCursor::last_below(partition_end).unwrap_or(from)
+                    }
+                    None => diesel_streamer::SerialCursor::chunk_end(from, chunk_size),
+                },
+                to,
+            );
+            let shard_filter = $options.shard.as_ref().map(|shard| {
+                diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+            });
+
+            let plan = match shard_filter {
+                Some(shard_filter) => match diesel_streamer::__explain(
+                    $query
+                        .filter($cursor_field.eq_any(from..=chunk_to))
+                        .filter(shard_filter)
+                        $(.select($select))?,
+                    backend_of(&*$conn),
+                    analyze,
+                ) {
+                    Some(explain) => explain.load::<String>($conn)$(.$await)?.map(Some),
+                    None => Ok(None),
+                },
+                None => match diesel_streamer::__explain(
+                    $query
+                        .filter($cursor_field.eq_any(from..=chunk_to))
+                        $(.select($select))?,
+                    backend_of(&*$conn),
+                    analyze,
+                ) {
+                    Some(explain) => explain.load::<String>($conn)$(.$await)?.map(Some),
+                    None => Ok(None),
+                },
+            };
+
+            (Some((from, to)), Some(from..=chunk_to), plan)
+        } else {
+            (None, None, Ok(None))
+        };
+
+        plan.map(|plan| {
+            let dry_run = diesel_streamer::__dry_run_report(
+                &$options,
+                bounds,
+                chunk_size,
+                sample_chunk,
+                plan,
+                analyze,
+                &$cursor_field,
+                backend_of(&*$conn),
+            );
+            instrument.record_dry_run(&dry_run);
+
+            dry_run
+        })
+    }};
+}
+
+/// Evaluates to its first argument, e.g. to fall back to a default for an
+/// optional macro argument.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __first {
+    ( $first:expr $(, $rest:expr)* ) => {
+        $first
+    };
+}
@@ -0,0 +1,447 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use std::convert::TryFrom;
+
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::query_builder::SqlQuery;
+use diesel::row::{Field, NamedRow, Row};
+use diesel::sql_types::{BigInt, Bool, Double, Float, Integer, SmallInt, Text, Untyped};
+use diesel::QueryableByName;
+use diesel_dynamic_schema::{Column, DynamicSelectClause, Table};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::bounds::{quote_identifiers, quote_literal};
+use crate::{OnProgress, Progress, StreamEnd, StreamOptions};
+
+/// The columns decoded as they are. Other columns are selected through
+/// `to_json`, or as text for numerics, to keep their precision.
+const NATIVE_TYPES: &[&str] = &[
+    "bool", "int2", "int4", "int8", "float4", "float8", "text", "varchar", "bpchar", "name",
+    "json", "jsonb",
+];
+
+/// The types a dynamic table's cursor may have.
+const CURSOR_TYPES: &[&str] = &["int2", "int4", "int8"];
+
+/// A Postgres table known by name at runtime only, streamed by
+/// `stream_dynamic_table!` through `diesel-dynamic-schema`, without a
+/// `table!` schema or a `Queryable` struct.
+///
+/// Its columns are read from `information_schema` with
+/// [`columns_query`](Self::columns_query). The cursor is either named, or
+/// the table's primary key, which must then be a single column. Either way,
+/// it must be a `SMALLINT`, `INTEGER` or `BIGINT` column, streamed as `i64`.
+///
+/// Every method returns a plain query, to run with either the sync or the
+/// async `RunQueryDsl`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicTable {
+    pub schema: String,
+    pub name: String,
+    pub columns: Vec<DynamicColumn>,
+    pub cursor: String,
+}
+
+/// A column of a [`DynamicTable`], as read from `information_schema`.
+#[derive(Debug, Clone, PartialEq, Eq, QueryableByName)]
+pub struct DynamicColumn {
+    #[diesel(sql_type = Text)]
+    pub table_schema: String,
+    #[diesel(sql_type = Text)]
+    pub table_name: String,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    /// Postgres' name of the column's type, e.g. `int4` or `varchar`.
+    #[diesel(sql_type = Text)]
+    pub type_name: String,
+    #[diesel(sql_type = Bool)]
+    pub primary_key: bool,
+}
+
+/// Why a [`DynamicTable`] could not be streamed.
+#[derive(Debug, PartialEq)]
+pub enum DynamicTableError {
+    /// Its columns could not be read from `information_schema`.
+    Query(diesel::result::Error),
+    /// The table does not exist, or is not visible to the connection.
+    NotFound(String),
+    /// No cursor was named and the table has no primary key.
+    NoPrimaryKey(String),
+    /// No cursor was named and the table's primary key spans several
+    /// columns.
+    CompositePrimaryKey(String),
+    /// The cursor is not a column of the table.
+    UnknownColumn { table: String, column: String },
+    /// The cursor is not an integer column.
+    UnsupportedCursor { column: String, type_name: String },
+}
+
+impl fmt::Display for DynamicTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DynamicTableError::Query(error) => {
+                write!(f, "the table's columns could not be read: {error}")
+            }
+            DynamicTableError::NotFound(table) => write!(f, "table {table} does not exist"),
+            DynamicTableError::NoPrimaryKey(table) => {
+                write!(f, "table {table} has no primary key to stream by")
+            }
+            DynamicTableError::CompositePrimaryKey(table) => {
+                write!(f, "table {table} has a composite primary key")
+            }
+            DynamicTableError::UnknownColumn { table, column } => {
+                write!(f, "table {table} has no column {column}")
+            }
+            DynamicTableError::UnsupportedCursor { column, type_name } => write!(
+                f,
+                "column {column} of type {type_name} cannot be streamed by, only integer columns can"
+            ),
+        }
+    }
+}
+
+impl Error for DynamicTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DynamicTableError::Query(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for DynamicTableError {
+    fn from(error: diesel::result::Error) -> Self {
+        DynamicTableError::Query(error)
+    }
+}
+
+impl DynamicTable {
+    /// Reads the columns of `table`, resolved as Postgres would resolve it
+    /// in a query, e.g. `users` or `public.users`, returning a
+    /// [`DynamicColumn`] per column.
+    pub fn columns_query(table: &str) -> SqlQuery {
+        diesel::sql_query(format!(
+            r"
+SELECT
+    c.table_schema::TEXT AS table_schema,
+    c.table_name::TEXT AS table_name,
+    c.column_name::TEXT AS name,
+    c.udt_name::TEXT AS type_name,
+    EXISTS (
+        SELECT 1
+        FROM information_schema.table_constraints AS tc
+        JOIN information_schema.key_column_usage AS kcu
+            ON kcu.constraint_schema = tc.constraint_schema
+            AND kcu.constraint_name = tc.constraint_name
+        WHERE tc.constraint_type = 'PRIMARY KEY'
+            AND tc.table_schema = c.table_schema
+            AND tc.table_name = c.table_name
+            AND kcu.column_name = c.column_name
+    ) AS primary_key
+FROM information_schema.columns AS c
+WHERE to_regclass(quote_ident(c.table_schema) || '.' || quote_ident(c.table_name)) = to_regclass({table})
+ORDER BY c.ordinal_position
+",
+            table = quote_literal(table),
+        ))
+    }
+
+    /// The table named `table`, made of `columns` as loaded by
+    /// [`columns_query`](Self::columns_query), streamed by `cursor`, or by
+    /// its primary key when `None`.
+    ///
+    /// # Errors
+    ///
+    /// When there are no columns, or when the cursor is missing or not an
+    /// integer column.
+    pub fn new(
+        table: &str,
+        columns: Vec<DynamicColumn>,
+        cursor: Option<&str>,
+    ) -> Result<Self, DynamicTableError> {
+        let first_column = columns
+            .first()
+            .ok_or_else(|| DynamicTableError::NotFound(table.to_owned()))?;
+        let (schema, name) = (
+            first_column.table_schema.clone(),
+            first_column.table_name.clone(),
+        );
+
+        let cursor_column = if let Some(cursor) = cursor {
+            columns
+                .iter()
+                .find(|column| column.name == cursor)
+                .ok_or_else(|| DynamicTableError::UnknownColumn {
+                    table: table.to_owned(),
+                    column: cursor.to_owned(),
+                })?
+        } else {
+            let mut primary_key = columns.iter().filter(|column| column.primary_key);
+
+            match (primary_key.next(), primary_key.next()) {
+                (Some(column), None) => column,
+                (None, _) => return Err(DynamicTableError::NoPrimaryKey(table.to_owned())),
+                (Some(_), Some(_)) => {
+                    return Err(DynamicTableError::CompositePrimaryKey(table.to_owned()))
+                }
+            }
+        };
+
+        if !CURSOR_TYPES.contains(&cursor_column.type_name.as_str()) {
+            return Err(DynamicTableError::UnsupportedCursor {
+                column: cursor_column.name.clone(),
+                type_name: cursor_column.type_name.clone(),
+            });
+        }
+
+        let cursor = cursor_column.name.clone();
+
+        Ok(DynamicTable {
+            schema,
+            name,
+            columns,
+            cursor,
+        })
+    }
+
+    /// The table, to query.
+    #[must_use]
+    pub fn source(&self) -> Table<String, String> {
+        diesel_dynamic_schema::schema(self.schema.clone()).table(self.name.clone())
+    }
+
+    /// The cursor column, typed as its [`cursor_type`](Self::cursor_type).
+    #[must_use]
+    pub fn cursor_column<ST>(&self) -> Column<Table<String, String>, String, ST> {
+        self.source().column(self.cursor.clone())
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    pub fn cursor_type(&self) -> CursorType {
+        let type_name = self
+            .columns
+            .iter()
+            .find(|column| column.name == self.cursor)
+            .map(|column| column.type_name.as_str());
+
+        match type_name {
+            Some("int2") => CursorType::SmallInt,
+            Some("int4") => CursorType::Integer,
+            _ => CursorType::BigInt,
+        }
+    }
+
+    /// Selects every column, to load as [`DynamicRow`]s.
+    #[must_use]
+    pub fn select_clause(&self) -> DynamicSelectClause<'static, Pg, Table<String, String>> {
+        let source = self.source();
+        let mut select_clause = DynamicSelectClause::new();
+
+        for column in &self.columns {
+            if NATIVE_TYPES.contains(&column.type_name.as_str()) {
+                select_clause.add_field(source.column::<Untyped, _>(column.name.clone()));
+                continue;
+            }
+
+            let identifiers = [self.schema.clone(), self.name.clone(), column.name.clone()];
+            let expression = if column.type_name == "numeric" {
+                format!("CAST({} AS TEXT)", quote_identifiers(&identifiers))
+            } else {
+                format!("to_json({})", quote_identifiers(&identifiers))
+            };
+
+            select_clause.add_field(diesel::dsl::sql::<Untyped>(&format!(
+                "{} AS {}",
+                expression,
+                quote_identifiers(std::slice::from_ref(&column.name))
+            )));
+        }
+
+        select_clause
+    }
+}
+
+/// A row streamed by `stream_dynamic_table!`: the value of every column, by
+/// column name.
+///
+/// Integers, floats, booleans, strings and JSON are decoded as such,
+/// numerics as strings to keep their precision, and any other type as its
+/// `to_json` representation, e.g. timestamps as ISO 8601 strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DynamicRow(pub Map<String, Value>);
+
+impl Deref for DynamicRow {
+    type Target = Map<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for DynamicRow {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<DynamicRow> for Value {
+    fn from(row: DynamicRow) -> Self {
+        Value::Object(row.0)
+    }
+}
+
+impl Serialize for DynamicRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl QueryableByName<Pg> for DynamicRow {
+    fn build<'a>(row: &impl NamedRow<'a, Pg>) -> deserialize::Result<Self> {
+        (0..row.field_count())
+            .map(|index| {
+                let field =
+                    Row::get(row, index).ok_or("the row has fewer fields than announced")?;
+                let name = field.field_name().ok_or("a field has no name")?.to_owned();
+
+                Ok((name, decode_value(field.value())?))
+            })
+            .collect::<deserialize::Result<_>>()
+            .map(DynamicRow)
+    }
+}
+
+fn decode_value(value: Option<PgValue<'_>>) -> deserialize::Result<Value> {
+    let Some(value) = value else {
+        return Ok(Value::Null);
+    };
+
+    match value.get_oid().get() {
+        16 => <bool as FromSql<Bool, Pg>>::from_sql(value).map(Value::from),
+        21 => <i16 as FromSql<SmallInt, Pg>>::from_sql(value).map(Value::from),
+        23 => <i32 as FromSql<Integer, Pg>>::from_sql(value).map(Value::from),
+        20 => <i64 as FromSql<BigInt, Pg>>::from_sql(value).map(Value::from),
+        700 => <f32 as FromSql<Float, Pg>>::from_sql(value).map(Value::from),
+        701 => <f64 as FromSql<Double, Pg>>::from_sql(value).map(Value::from),
+        19 | 25 | 1042 | 1043 => <String as FromSql<Text, Pg>>::from_sql(value).map(Value::from),
+        114 => Ok(serde_json::from_slice(value.as_bytes())?),
+        // jsonb starts with its format's version
+        3802 => match value.as_bytes().split_first() {
+            Some((1, json)) => Ok(serde_json::from_slice(json)?),
+            _ => Err("unsupported jsonb format".into()),
+        },
+        oid => Err(format!("unsupported type oid {oid}").into()),
+    }
+}
+
+/// The types a dynamic table's cursor may have, each streamed with the
+/// matching Rust integer.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorType {
+    SmallInt,
+    Integer,
+    BigInt,
+}
+
+/// A cursor type the `i64` options of a dynamic stream narrow into.
+#[doc(hidden)]
+pub trait DynamicCursor: Copy + Into<i64> + TryFrom<i64> + 'static {
+    const MIN: Self;
+    const MAX: Self;
+
+    /// `value`, saturated at the type's bounds. No value of the type lies
+    /// past them, so ranges keep covering the same values.
+    #[must_use]
+    fn saturate(value: i64) -> Self {
+        Self::try_from(value).unwrap_or(if value < 0 { Self::MIN } else { Self::MAX })
+    }
+
+    /// Whether `value` lies above every value of the type.
+    fn above(value: i64) -> bool {
+        value > Self::MAX.into()
+    }
+
+    /// Whether `value` lies below every value of the type.
+    fn below(value: i64) -> bool {
+        value < Self::MIN.into()
+    }
+}
+
+impl DynamicCursor for i16 {
+    const MIN: Self = i16::MIN;
+    const MAX: Self = i16::MAX;
+}
+
+impl DynamicCursor for i32 {
+    const MIN: Self = i32::MIN;
+    const MAX: Self = i32::MAX;
+}
+
+impl DynamicCursor for i64 {
+    const MIN: Self = i64::MIN;
+    const MAX: Self = i64::MAX;
+}
+
+/// Narrows `options` to the cursor type `T` of a dynamic table, with a
+/// chunk size of `default_chunk_size` unless set. `None` when `from` lies
+/// above the type's values or `to` below them, so no value is in range.
+#[doc(hidden)]
+pub fn __narrow_options<T: DynamicCursor>(
+    options: StreamOptions<i64>,
+    default_chunk_size: i64,
+) -> Option<StreamOptions<T>> {
+    if options.from.is_some_and(T::above) || options.to.is_some_and(T::below) {
+        return None;
+    }
+
+    let mut narrowed = StreamOptions::new();
+
+    narrowed.chunk_size = Some(T::saturate(
+        options.chunk_size.unwrap_or(default_chunk_size),
+    ));
+    narrowed.from = options.from.map(T::saturate);
+    narrowed.to = options.to.map(T::saturate);
+    narrowed.upper_bound = options.upper_bound;
+    narrowed.bounds = options.bounds;
+    narrowed.partitions = options.partitions.map(|partitions| {
+        partitions
+            .into_iter()
+            .map(|range| T::saturate(range.start)..T::saturate(range.end))
+            .collect()
+    });
+    narrowed.shard = options.shard;
+    narrowed.name = options.name;
+    narrowed.transaction_per_chunk = options.transaction_per_chunk;
+    narrowed.lock_timeout = options.lock_timeout;
+    narrowed.retries = options.retries;
+    narrowed.pause = options.pause;
+    narrowed.cancellation = options.cancellation;
+    narrowed.progress = options.progress.map(|mut on_progress| -> OnProgress<T> {
+        Box::new(move |progress: &Progress<T>| on_progress(&progress.map(Into::into)))
+    });
+
+    Some(narrowed)
+}
+
+/// Widens the cursor of a dynamic stream's end back to `i64`.
+#[doc(hidden)]
+pub fn __widen_stream_end<T: DynamicCursor, B>(stream_end: StreamEnd<T, B>) -> StreamEnd<i64, B> {
+    match stream_end {
+        StreamEnd::Completed { cursor } => StreamEnd::Completed {
+            cursor: cursor.map(Into::into),
+        },
+        StreamEnd::Cancelled { cursor } => StreamEnd::Cancelled {
+            cursor: cursor.map(Into::into),
+        },
+        StreamEnd::Stopped { cursor, reason } => StreamEnd::Stopped {
+            cursor: cursor.map(Into::into),
+            reason,
+        },
+    }
+}
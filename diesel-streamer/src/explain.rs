@@ -0,0 +1,228 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+
+use diesel::backend::Backend;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+use diesel::sql_types::Text;
+use diesel::QueryResult;
+
+use crate::{SerialCursor, StreamOptions};
+
+/// What a stream would do, as reported by `dry_run!` without processing any
+/// row.
+///
+/// On Postgres, `plan` holds the `EXPLAIN` (or `EXPLAIN ANALYZE`) of the
+/// query loading the first chunk, and `warnings` flags a plan scanning the
+/// cursor field's table sequentially instead of through an index on the
+/// cursor field.
+///
+/// The check only looks for a `Seq Scan` (or `Parallel Seq Scan`) node on a
+/// table named like the cursor field's, as plans leave out schemas: a table
+/// of the same name in another schema is flagged too. Other nodes reading
+/// more than the chunk, e.g. a `Bitmap Heap Scan` or an `Index Scan` through
+/// an index on another column, are not flagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DryRun<T> {
+    /// The resolved bounds, or `None` when nothing would be streamed.
+    pub from: Option<T>,
+    pub to: Option<T>,
+    pub chunk_size: T,
+    /// The number of chunks the stream would load, at the resolved bounds.
+    pub chunks: u64,
+    /// The first chunk, whose query was explained.
+    pub sample_chunk: Option<RangeInclusive<T>>,
+    /// The lines of the query plan, empty on backends without `EXPLAIN`
+    /// support.
+    pub plan: Vec<String>,
+    /// Whether the sample chunk was run through `EXPLAIN ANALYZE`.
+    pub analyzed: bool,
+    pub sequential_scan: bool,
+    pub warnings: Vec<String>,
+}
+
+impl<T: fmt::Debug> fmt::Display for DryRun<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => writeln!(
+                f,
+                "bounds: {:?}..={:?}, chunk size: {:?}, chunks: {}",
+                from, to, self.chunk_size, self.chunks
+            )?,
+            _ => writeln!(f, "nothing to stream")?,
+        }
+
+        if let Some(sample_chunk) = &self.sample_chunk {
+            let explain = if self.analyzed {
+                "EXPLAIN ANALYZE"
+            } else {
+                "EXPLAIN"
+            };
+            writeln!(f, "{explain} of chunk {sample_chunk:?}:")?;
+        }
+
+        for line in &self.plan {
+            writeln!(f, "  {line}")?;
+        }
+
+        for warning in &self.warnings {
+            writeln!(f, "warning: {warning}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Prefixes a query with `EXPLAIN`, keeping its bind parameters.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct Explain<Q> {
+    prefix: &'static str,
+    query: Q,
+}
+
+impl<Q> Query for Explain<Q> {
+    type SqlType = Text;
+}
+
+impl<Q> QueryId for Explain<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<DB: Backend, Q: QueryFragment<DB>> QueryFragment<DB> for Explain<Q> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, DB>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+        out.push_sql(self.prefix);
+        self.query.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q, Conn> diesel::RunQueryDsl<Conn> for Explain<Q> {}
+
+/// `query` prefixed with `EXPLAIN`, if `DB` reports plans as lines of text.
+#[doc(hidden)]
+#[cfg_attr(
+    not(feature = "postgres"),
+    allow(unused_variables, clippy::needless_pass_by_value)
+)]
+pub fn __explain<DB: 'static, Q>(
+    query: Q,
+    _backend: PhantomData<DB>,
+    analyze: bool,
+) -> Option<Explain<Q>> {
+    #[cfg(feature = "postgres")]
+    {
+        if std::any::TypeId::of::<DB>() == std::any::TypeId::of::<diesel::pg::Pg>() {
+            let prefix = if analyze {
+                "EXPLAIN ANALYZE "
+            } else {
+                "EXPLAIN "
+            };
+
+            return Some(Explain { prefix, query });
+        }
+    }
+
+    None
+}
+
+/// Reports a dry run on behalf of `dry_run!`, once it resolved the bounds
+/// and explained the sample chunk.
+#[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
+pub fn __dry_run_report<T, DB, C>(
+    options: &StreamOptions<T>,
+    bounds: Option<(T, T)>,
+    chunk_size: T,
+    sample_chunk: Option<RangeInclusive<T>>,
+    plan: Option<Vec<String>>,
+    analyzed: bool,
+    cursor_field: &C,
+    _backend: PhantomData<DB>,
+) -> DryRun<T>
+where
+    T: SerialCursor,
+    DB: Backend + Default,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    let chunks = bounds.map_or(0, |(from, to)| count_chunks(options, from, to, chunk_size));
+    let plan = plan.unwrap_or_default();
+
+    let mut sequential_scan = false;
+    let mut warnings = vec![];
+
+    if let Some((table_identifiers, column)) = crate::bounds::cursor_identifiers(cursor_field) {
+        let table = table_identifiers.last().cloned().unwrap_or_default();
+
+        sequential_scan = plan.iter().any(|line| {
+            line.split("Seq Scan on ")
+                .nth(1)
+                .and_then(|scanned| scanned.split_whitespace().next())
+                .is_some_and(|scanned| scanned.trim_matches('"') == table)
+        });
+
+        if sequential_scan {
+            warnings.push(format!(
+                "the chunk query scans {table} sequentially instead of through an index on {column}"
+            ));
+        }
+    }
+
+    DryRun {
+        from: bounds.map(|(from, _)| from),
+        to: bounds.map(|(_, to)| to),
+        chunk_size,
+        chunks,
+        sample_chunk,
+        plan,
+        analyzed,
+        sequential_scan,
+        warnings,
+    }
+}
+
+/// Counts the chunks of `from..=to`, following the partitions of `options`
+/// like `__stream_chunks!` does.
+fn count_chunks<T: SerialCursor>(options: &StreamOptions<T>, from: T, to: T, chunk_size: T) -> u64 {
+    let mut cursor = i128::from(from.as_i64());
+    let end = i128::from(to.as_i64()) + 1;
+    let chunk_size = i128::from(chunk_size.as_i64()).max(1);
+    let mut chunks = 0;
+
+    for partition in options.partitions.iter().flatten() {
+        let (start, stop) = (
+            i128::from(partition.start.as_i64()),
+            i128::from(partition.end.as_i64()),
+        );
+
+        if cursor >= end {
+            break;
+        }
+
+        if stop <= cursor {
+            continue;
+        }
+
+        if cursor < start {
+            chunks += 1;
+            cursor = start.min(end);
+
+            if cursor >= end {
+                break;
+            }
+        }
+
+        chunks += 1;
+        cursor = stop.min(end);
+    }
+
+    if cursor < end {
+        chunks += (end - cursor + chunk_size - 1) / chunk_size;
+    }
+
+    u64::try_from(chunks).unwrap_or(u64::MAX)
+}
@@ -67,29 +67,28 @@ macro_rules! get_serial_table_async_stream {
         };
 
         use diesel_async::AsyncConnection;
-        use futures_util::{Stream, StreamExt};
-        use pin_project_lite::pin_project;
+        use diesel_streamer::__private::futures_util::{ready, Stream, StreamExt};
+        use diesel_streamer::__private::pin_project_lite::pin_project;
+        use diesel_streamer::__private::tokio::sync::Mutex;
         use std::ops::DerefMut;
         use std::sync::Arc;
-        use tokio::sync::Mutex;
-
-        use crate::ChaindexingRepo;
 
         type DataStream = Vec<$table_struct>;
 
         enum SerialTableStreamState<'a> {
             GetFromAndToFuture,
             PollFromAndToFuture(
-                Pin<Box<dyn Future<Output = ($fromToType, $fromToType)> + Send + 'a>>,
+                Pin<Box<dyn Future<Output = Option<($fromToType, $fromToType)>> + Send + 'a>>,
             ),
             GetDataStreamFuture(($fromToType, $fromToType)),
             PollDataStreamFuture(
                 (
                     Pin<Box<dyn Future<Output = DataStream> + Send + 'a>>,
-                    $fromToType,
+                    Option<$fromToType>,
                     $fromToType,
                 ),
             ),
+            Done,
         };
 
         pin_project!(
@@ -113,14 +112,14 @@ macro_rules! get_serial_table_async_stream {
                 use diesel::prelude::*;
                 use diesel_async::RunQueryDsl;
 
-                use futures_util::Future;
+                use diesel_streamer::__private::futures_util::Future;
                 use std::time::Duration;
                 use std::{
                     pin::Pin,
                     task::{Context, Poll},
                 };
 
-                use futures_util::FutureExt;
+                use diesel_streamer::__private::futures_util::FutureExt;
 
                 let mut this = self.project();
                 let from = *this.from;
@@ -135,26 +134,24 @@ macro_rules! get_serial_table_async_stream {
                                 let mut conn = conn.lock().await;
 
                                 let from = match from {
-                                    Some(from) => from,
+                                    Some(from) => Some(from),
                                     None => $query
                                         .select(min($cursor_field))
-                                        .get_result::<Option<$fromToType>>(&mut conn)
+                                        .get_result::<Option<$fromToType>>(&mut *conn)
                                         .await
-                                        .unwrap()
-                                        .unwrap_or(0),
+                                        .unwrap(),
                                 };
 
                                 let to = match to {
-                                    Some(to) => to,
+                                    Some(to) => Some(to),
                                     None => $query
                                         .select(max($cursor_field))
-                                        .get_result::<Option<$fromToType>>(&mut conn)
+                                        .get_result::<Option<$fromToType>>(&mut *conn)
                                         .await
-                                        .unwrap()
-                                        .unwrap_or(0),
+                                        .unwrap(),
                                 };
 
-                                (from, to)
+                                from.zip(to)
                             }
                             .boxed(),
                         );
@@ -163,10 +160,13 @@ macro_rules! get_serial_table_async_stream {
                         Poll::Pending
                     }
                     SerialTableStreamState::PollFromAndToFuture(from_and_to_future) => {
-                        let (from, to): ($fromToType, $fromToType) =
-                            futures_util::ready!(from_and_to_future.as_mut().poll(cx));
+                        let bounds: Option<($fromToType, $fromToType)> =
+                            ready!(from_and_to_future.as_mut().poll(cx));
 
-                        *this.state = SerialTableStreamState::GetDataStreamFuture((from, to));
+                        *this.state = match bounds {
+                            Some(bounds) => SerialTableStreamState::GetDataStreamFuture(bounds),
+                            None => SerialTableStreamState::Done,
+                        };
 
                         cx.waker().wake_by_ref();
 
@@ -180,14 +180,20 @@ macro_rules! get_serial_table_async_stream {
                             Poll::Ready(None)
                         } else {
                             let conn = this.conn.clone();
-                            let chunk_limit = from + (*this.chunk_size as $fromToType);
+                            let chunk_to = std::cmp::min(
+                                diesel_streamer::SerialCursor::chunk_end(
+                                    from,
+                                    *this.chunk_size as $fromToType,
+                                ),
+                                to,
+                            );
 
                             let mut data_stream_future = async move {
                                 let mut conn = conn.lock().await;
 
                                 $query
-                                    .filter($cursor_field.eq_any(from..chunk_limit))
-                                    .load(&mut conn)
+                                    .filter($cursor_field.eq_any(from..=chunk_to))
+                                    .load(&mut *conn)
                                     .await
                                     .unwrap()
                             }
@@ -195,7 +201,7 @@ macro_rules! get_serial_table_async_stream {
 
                             *this.state = SerialTableStreamState::PollDataStreamFuture((
                                 data_stream_future,
-                                chunk_limit,
+                                diesel_streamer::SerialCursor::checked_next(chunk_to),
                                 to,
                             ));
 
@@ -209,16 +215,21 @@ macro_rules! get_serial_table_async_stream {
                         next_from,
                         to,
                     )) => {
-                        let streamed_data =
-                            futures_util::ready!(data_stream_future.as_mut().poll(cx));
+                        let streamed_data = ready!(data_stream_future.as_mut().poll(cx));
 
-                        *this.state =
-                            SerialTableStreamState::GetDataStreamFuture((*next_from, *to));
+                        *this.state = match *next_from {
+                            Some(next_from) => {
+                                SerialTableStreamState::GetDataStreamFuture((next_from, *to))
+                            }
+                            // Nothing follows the cursor type's maximum
+                            None => SerialTableStreamState::Done,
+                        };
 
                         cx.waker().wake_by_ref();
 
                         Poll::Ready(Some(streamed_data))
                     }
+                    SerialTableStreamState::Done => Poll::Ready(None),
                 }
             }
         }
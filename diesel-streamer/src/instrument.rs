@@ -0,0 +1,342 @@
+//! Instrumentation hooks called by the streaming macros.
+//!
+//! The macros expand inside the caller's crate, where `cfg(feature = ...)`
+//! would check the caller's features instead of ours, so the macros always
+//! call these hooks and the hooks decide what to do. Every hook is a no-op
+//! unless the `tracing` or `metrics` feature is enabled.
+
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::marker::PhantomData;
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+use std::time::Instant;
+
+#[cfg(feature = "tracing")]
+use tracing::field::{debug, Empty};
+
+/// Instruments a whole stream, from resolving its bounds to its last chunk.
+pub struct StreamInstrument {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "metrics")]
+    metrics: StreamMetrics,
+}
+
+impl StreamInstrument {
+    #[allow(unused_variables)]
+    #[must_use]
+    pub fn start(name: Option<&str>) -> Self {
+        StreamInstrument {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "stream_serial_table",
+                name = name,
+                from = Empty,
+                to = Empty,
+                chunk_size = Empty
+            ),
+            #[cfg(feature = "metrics")]
+            metrics: StreamMetrics::new(name.unwrap_or("default")),
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_bounds<T: Debug>(&self, from: T, to: T, chunk_size: T) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("from", debug(&from));
+            self.span.record("to", debug(&to));
+            self.span.record("chunk_size", debug(&chunk_size));
+
+            tracing::debug!(parent: &self.span, from = ?from, to = ?to, "resolved stream bounds");
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_estimate(&self, estimate: Option<&crate::BoundsEstimate>) {
+        #[cfg(feature = "tracing")]
+        if let Some(estimate) = estimate {
+            tracing::debug!(
+                parent: &self.span,
+                from = ?estimate.from_estimate,
+                to = ?estimate.to_estimate,
+                rows = ?estimate.rows_estimate,
+                "estimated stream bounds"
+            );
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_dry_run<T: Debug>(&self, dry_run: &crate::DryRun<T>) {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::info!(
+                parent: &self.span,
+                chunks = dry_run.chunks,
+                sample_chunk = ?dry_run.sample_chunk,
+                plan = %dry_run.plan.join("\n"),
+                "explained stream"
+            );
+
+            for warning in &dry_run.warnings {
+                tracing::warn!(parent: &self.span, "{}", warning);
+            }
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_upper_bound_refresh<T: Debug>(&self, to: T) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("to", debug(&to));
+
+            tracing::debug!(parent: &self.span, to = ?to, "refreshed upper bound");
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_cancellation<T: Debug>(&self, cursor: T) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(parent: &self.span, cursor = ?cursor, "stream cancelled");
+    }
+
+    #[allow(unused_variables)]
+    pub fn record_stop<T: Debug>(&self, cursor: T) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(parent: &self.span, cursor = ?cursor, "stream stopped by processor");
+    }
+
+    #[allow(unused_variables)]
+    #[must_use]
+    pub fn chunk<T: Debug>(&self, from: T, to: T) -> ChunkInstrument {
+        ChunkInstrument {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                parent: &self.span,
+                "chunk",
+                from = ?from,
+                to = ?to,
+                rows = Empty,
+                query_duration = Empty,
+                processor_duration = Empty
+            ),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            started_at: Instant::now(),
+        }
+    }
+}
+
+/// Instruments loading and processing a single chunk.
+pub struct ChunkInstrument {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "metrics")]
+    metrics: StreamMetrics,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    started_at: Instant,
+}
+
+impl ChunkInstrument {
+    /// Logs the SQL of `query` at trace level.
+    #[cfg(feature = "tracing")]
+    pub fn trace_query<DB, Q>(&self, query: &Q, _backend: PhantomData<DB>)
+    where
+        DB: diesel::backend::Backend + Default,
+        DB::QueryBuilder: Default,
+        Q: diesel::query_builder::QueryFragment<DB>,
+    {
+        tracing::trace!(
+            parent: &self.span,
+            sql = %diesel::debug_query::<DB, _>(query),
+            "loading chunk"
+        );
+    }
+
+    /// Logs the SQL of `query` at trace level.
+    #[cfg(not(feature = "tracing"))]
+    pub fn trace_query<DB, Q>(&self, _query: &Q, _backend: PhantomData<DB>) {}
+
+    /// Records the result of loading the chunk's rows.
+    pub fn record_query<R, E: Display>(&mut self, result: &Result<Vec<R>, E>) {
+        self.record_rows(result.as_ref().map(Vec::len));
+    }
+
+    /// Records the result of a statement writing the chunk's rows.
+    pub fn record_execute<E: Display>(&mut self, result: &Result<usize, E>) {
+        self.record_rows(result.as_ref().map(|rows| *rows));
+    }
+
+    #[allow(unused_variables)]
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "metrics")),
+        allow(clippy::unused_self)
+    )]
+    fn record_rows<E: Display>(&mut self, rows: Result<usize, &E>) {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        {
+            let query_duration = self.started_at.elapsed();
+            self.started_at = Instant::now();
+
+            #[cfg(feature = "tracing")]
+            {
+                self.span.record("query_duration", debug(query_duration));
+
+                match rows {
+                    Ok(rows) => {
+                        self.span.record("rows", rows);
+                    }
+                    Err(error) => {
+                        tracing::error!(parent: &self.span, error = %error, "chunk query failed");
+                    }
+                }
+            }
+
+            #[cfg(feature = "metrics")]
+            self.metrics.record_rows(query_duration, rows);
+        }
+    }
+
+    /// Runs a synchronous processor inside the chunk's span.
+    #[cfg(feature = "tracing")]
+    pub fn in_scope<R>(&self, process: impl FnOnce() -> R) -> R {
+        self.span.in_scope(process)
+    }
+
+    /// Runs a synchronous processor inside the chunk's span.
+    #[cfg(not(feature = "tracing"))]
+    pub fn in_scope<R>(&self, process: impl FnOnce() -> R) -> R {
+        process()
+    }
+
+    /// Runs an asynchronous processor inside the chunk's span.
+    #[cfg(feature = "tracing")]
+    pub fn instrument<F: Future>(&self, process: F) -> impl Future<Output = F::Output> {
+        tracing::Instrument::instrument(process, self.span.clone())
+    }
+
+    /// Runs an asynchronous processor inside the chunk's span.
+    #[cfg(not(feature = "tracing"))]
+    pub fn instrument<F: Future>(&self, process: F) -> impl Future<Output = F::Output> {
+        process
+    }
+
+    /// Records the result of inserting the chunk's rows elsewhere, as
+    /// `copy_table!` does once the chunk is loaded.
+    #[allow(unused_variables)]
+    pub fn record_insert<E: Display>(&self, result: &Result<usize, E>) {
+        if let Err(error) = result {
+            #[cfg(feature = "tracing")]
+            tracing::error!(parent: &self.span, error = %error, "chunk insert failed");
+
+            #[cfg(feature = "metrics")]
+            self.metrics.errors.increment(1);
+        }
+    }
+
+    /// Records the processor stopping the stream with `ControlFlow::Break`
+    /// or, when `failed`, with `Err`. Only failures count as errors.
+    #[allow(unused_variables)]
+    pub fn record_break(&self, failed: bool) {
+        #[cfg(feature = "tracing")]
+        if failed {
+            tracing::warn!(parent: &self.span, "processor failed, stopping the stream");
+        } else {
+            tracing::info!(parent: &self.span, "processor stopped the stream");
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.stops.increment(1);
+
+            if failed {
+                self.metrics.errors.increment(1);
+            }
+        }
+    }
+
+    /// Records a failed chunk being tried again.
+    #[allow(unused_variables)]
+    pub fn record_retry(&self, attempt: usize) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(parent: &self.span, attempt = attempt, "retrying chunk");
+
+        #[cfg(feature = "metrics")]
+        self.metrics.retries.increment(1);
+    }
+
+    pub fn record_processor(&mut self) {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        {
+            let processor_duration = self.started_at.elapsed();
+
+            #[cfg(feature = "tracing")]
+            self.span
+                .record("processor_duration", debug(processor_duration));
+
+            #[cfg(feature = "metrics")]
+            self.metrics
+                .processor_duration
+                .record(processor_duration.as_secs_f64());
+        }
+    }
+}
+
+/// Metric handles of a stream, labeled with the stream's name.
+#[cfg(feature = "metrics")]
+#[derive(Clone)]
+struct StreamMetrics {
+    rows: metrics::Counter,
+    chunks: metrics::Counter,
+    empty_chunks: metrics::Counter,
+    retries: metrics::Counter,
+    stops: metrics::Counter,
+    errors: metrics::Counter,
+    query_duration: metrics::Histogram,
+    processor_duration: metrics::Histogram,
+    chunk_size: metrics::Histogram,
+}
+
+#[cfg(feature = "metrics")]
+impl StreamMetrics {
+    fn new(name: &str) -> Self {
+        let labels = [("stream", name.to_string())];
+
+        StreamMetrics {
+            rows: metrics::counter!("diesel_streamer_rows_total", &labels),
+            chunks: metrics::counter!("diesel_streamer_chunks_total", &labels),
+            empty_chunks: metrics::counter!("diesel_streamer_empty_chunks_total", &labels),
+            retries: metrics::counter!("diesel_streamer_retries_total", &labels),
+            stops: metrics::counter!("diesel_streamer_stops_total", &labels),
+            errors: metrics::counter!("diesel_streamer_errors_total", &labels),
+            query_duration: metrics::histogram!(
+                "diesel_streamer_chunk_query_duration_seconds",
+                &labels
+            ),
+            processor_duration: metrics::histogram!(
+                "diesel_streamer_processor_duration_seconds",
+                &labels
+            ),
+            chunk_size: metrics::histogram!("diesel_streamer_chunk_rows", &labels),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn record_rows<E>(&self, query_duration: std::time::Duration, rows: Result<usize, &E>) {
+        self.query_duration.record(query_duration.as_secs_f64());
+
+        match rows {
+            Ok(rows) => {
+                self.chunks.increment(1);
+                self.rows.increment(rows as u64);
+                self.chunk_size.record(rows as f64);
+
+                if rows == 0 {
+                    self.empty_chunks.increment(1);
+                }
+            }
+            Err(_) => self.errors.increment(1),
+        }
+    }
+}
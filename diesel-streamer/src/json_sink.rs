@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use serde::Serialize;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Writes the chunks of a stream as newline-delimited JSON (JSON Lines) to
+/// any [`Write`]: one JSON object per row and per line.
+///
+/// The writer is flushed after every chunk, so downstream tools can consume
+/// the output while the stream is still running.
+///
+/// # Examples
+///
+/// ```
+/// use diesel_streamer::JsonLinesSink;
+///
+/// let mut sink = JsonLinesSink::new(vec![]);
+///
+/// sink.write_chunk(&[(1, "Ada Lovelace"), (2, "Alan Turing")]).unwrap();
+///
+/// let json_lines = String::from_utf8(sink.into_inner()).unwrap();
+/// assert_eq!(json_lines, "[1,\"Ada Lovelace\"]\n[2,\"Alan Turing\"]\n");
+/// ```
+///
+/// Inside `stream_serial_table!`:
+///
+/// ```ignore
+/// let mut sink = JsonLinesSink::new(File::create("users.jsonl")?);
+///
+/// stream_serial_table!(users, id, conn, |users: Vec<User>| sink.write_chunk(&users));
+/// ```
+#[derive(Debug)]
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink { writer }
+    }
+
+    /// Writes a chunk of rows, then flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row cannot be serialized as JSON, or when writing fails.
+    pub fn write_chunk<T: Serialize>(&mut self, rows: &[T]) -> Result<(), serde_json::Error> {
+        for row in rows {
+            serde_json::to_writer(&mut self.writer, row)?;
+            self.writer
+                .write_all(b"\n")
+                .map_err(serde_json::Error::io)?;
+        }
+
+        self.writer.flush().map_err(serde_json::Error::io)
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Writes the chunks of a stream as newline-delimited JSON (JSON Lines) to
+/// any tokio `AsyncWrite`, e.g. the chunks of a `SerialTableStream`.
+///
+/// Every chunk is serialized in memory before being written out, and the
+/// writer is flushed after every chunk.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut sink = AsyncJsonLinesSink::new(tokio::io::stdout());
+///
+/// while let Some(users) = stream.next().await {
+///     sink.write_chunk(&users).await?;
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct AsyncJsonLinesSink<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "async")]
+impl<W: AsyncWrite + Unpin> AsyncJsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        AsyncJsonLinesSink {
+            writer,
+            buffer: vec![],
+        }
+    }
+
+    /// Writes a chunk of rows, then flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row cannot be serialized as JSON, or when writing fails.
+    pub async fn write_chunk<T: Serialize>(&mut self, rows: &[T]) -> Result<(), serde_json::Error> {
+        self.buffer.clear();
+
+        for row in rows {
+            serde_json::to_writer(&mut self.buffer, row)?;
+            self.buffer.push(b'\n');
+        }
+
+        self.write_buffer().await.map_err(serde_json::Error::io)
+    }
+
+    async fn write_buffer(&mut self) -> std::io::Result<()> {
+        self.writer.write_all(&self.buffer).await?;
+        self.writer.flush().await
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,283 @@
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::time::Duration;
+
+use diesel::pg::Pg;
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::sql_types::{Array, BigInt, Integer, Text};
+use diesel::QueryableByName;
+
+use crate::{SerialCursor, Shard};
+
+diesel::table! {
+    /// The work units of cooperatively streamed tables, as created by
+    /// [`LeaseCoordinator::CREATE_TABLE`].
+    diesel_streamer_leases (stream_name, unit_start) {
+        stream_name -> Text,
+        unit_start -> Int8,
+        unit_end -> Int8,
+        worker -> Nullable<Text>,
+        lease_expires_at -> Nullable<Timestamptz>,
+        heartbeat_at -> Nullable<Timestamptz>,
+        done -> Bool,
+        attempts -> Int4,
+    }
+}
+
+/// Shares the streaming of one table between any number of workers, e.g.
+/// the pods of a backfill, through work units stored in the
+/// `diesel_streamer_leases` table.
+///
+/// The cursor's range is divided into units once, by any of the workers.
+/// Each worker then claims units with `FOR UPDATE SKIP LOCKED`, so no two
+/// workers claim the same one, and holds a lease on its unit for
+/// `lease_duration`. The lease is renewed by heartbeats while the unit is
+/// streamed, and released once the unit is done. Units whose lease expired,
+/// e.g. because their worker died, are claimed again by the others.
+///
+/// Every method returns a plain query, its values bound as parameters, to
+/// run with either the sync or the async `RunQueryDsl`.
+/// `stream_leased_table!` runs the whole loop.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{plan_partitions, stream_leased_table, LeaseCoordinator};
+///
+/// let coordinator = LeaseCoordinator::new("backfill_events", hostname)
+///     .lease_duration(Duration::from_secs(60));
+///
+/// coordinator.create_table_query().execute(conn)?;
+///
+/// let units: Vec<Range<i64>> = plan_partitions!(events, id, conn, partitions = 1000);
+/// coordinator.plan_query(&units).execute(conn)?;
+///
+/// stream_leased_table!(events, id, conn, coordinator = coordinator, |events: Vec<Event>| {
+///     // do work here
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LeaseCoordinator {
+    pub stream_name: String,
+    pub worker: String,
+    pub lease_duration: Duration,
+    pub poll_interval: Duration,
+    pub shard: Option<Shard>,
+}
+
+impl LeaseCoordinator {
+    /// Creates the lease table, e.g. from a diesel migration.
+    pub const CREATE_TABLE: &'static str = r"
+CREATE TABLE IF NOT EXISTS diesel_streamer_leases (
+    stream_name TEXT NOT NULL,
+    unit_start BIGINT NOT NULL,
+    unit_end BIGINT NOT NULL,
+    worker TEXT,
+    lease_expires_at TIMESTAMPTZ,
+    heartbeat_at TIMESTAMPTZ,
+    done BOOLEAN NOT NULL DEFAULT FALSE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (stream_name, unit_start)
+)
+";
+
+    /// Coordinates the stream named `stream_name` on behalf of `worker`,
+    /// which must be unique among the workers, e.g. a hostname.
+    pub fn new(stream_name: impl Into<String>, worker: impl Into<String>) -> Self {
+        LeaseCoordinator {
+            stream_name: stream_name.into(),
+            worker: worker.into(),
+            lease_duration: Duration::from_secs(300),
+            poll_interval: Duration::from_secs(5),
+            shard: None,
+        }
+    }
+
+    /// How long a unit stays leased without a heartbeat before other
+    /// workers may claim it. Defaults to 5 minutes.
+    #[must_use]
+    pub fn lease_duration(mut self, lease_duration: Duration) -> Self {
+        self.lease_duration = lease_duration;
+        self
+    }
+
+    /// How long `stream_leased_table!` waits before claiming again when
+    /// every remaining unit is leased by another worker. Defaults to 5
+    /// seconds.
+    #[must_use]
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Only streams the rows of `shard` within each unit, as with
+    /// [`StreamOptions::shard`](crate::StreamOptions::shard), e.g. for the
+    /// workers of each shard to share that shard's units among themselves.
+    /// The stream name should then differ per shard.
+    #[must_use]
+    pub fn shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Runs [`CREATE_TABLE`](Self::CREATE_TABLE).
+    pub fn create_table_query(&self) -> SqlQuery {
+        diesel::sql_query(Self::CREATE_TABLE)
+    }
+
+    /// Stores `units` as the work units of the stream, e.g. as planned by
+    /// `plan_partitions!`. Units that already exist are left untouched, so
+    /// every worker may plan the same units on start. Units exclude their
+    /// end, so none covers the cursor type's maximum.
+    pub fn plan_query<T: SerialCursor>(&self, units: &[Range<T>]) -> LeaseQuery {
+        diesel::sql_query(
+            r"
+INSERT INTO diesel_streamer_leases (stream_name, unit_start, unit_end)
+SELECT $1, unit_start, unit_end
+FROM unnest($2::BIGINT[], $3::BIGINT[]) AS units (unit_start, unit_end)
+ON CONFLICT DO NOTHING
+",
+        )
+        .into_boxed()
+        .bind::<Text, _>(self.stream_name.clone())
+        .bind::<Array<BigInt>, _>(
+            units
+                .iter()
+                .map(|unit| unit.start.as_i64())
+                .collect::<Vec<_>>(),
+        )
+        .bind::<Array<BigInt>, _>(
+            units
+                .iter()
+                .map(|unit| unit.end.as_i64())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Leases the first unit that is neither done nor leased, returning a
+    /// [`LeaseRow`], or no rows when there is none.
+    pub fn claim_query(&self) -> LeaseQuery {
+        diesel::sql_query(
+            r"
+UPDATE diesel_streamer_leases
+SET worker = $2,
+    lease_expires_at = clock_timestamp() + $3 * INTERVAL '1 millisecond',
+    heartbeat_at = clock_timestamp(),
+    attempts = attempts + 1
+WHERE stream_name = $1
+    AND unit_start = (
+        SELECT unit_start
+        FROM diesel_streamer_leases
+        WHERE stream_name = $1
+            AND NOT done
+            AND (lease_expires_at IS NULL OR lease_expires_at <= clock_timestamp())
+        ORDER BY unit_start
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+    )
+RETURNING unit_start, unit_end, attempts
+",
+        )
+        .into_boxed()
+        .bind::<Text, _>(self.stream_name.clone())
+        .bind::<Text, _>(self.worker.clone())
+        .bind::<BigInt, _>(self.lease_duration_millis())
+    }
+
+    /// Renews the lease on `lease`'s unit. Affects no rows when the lease
+    /// was lost to another worker.
+    pub fn heartbeat_query<T: SerialCursor>(&self, lease: &Lease<T>) -> LeaseQuery {
+        diesel::sql_query(
+            r"
+UPDATE diesel_streamer_leases
+SET lease_expires_at = clock_timestamp() + $4 * INTERVAL '1 millisecond',
+    heartbeat_at = clock_timestamp()
+WHERE stream_name = $1 AND unit_start = $2 AND worker = $3 AND NOT done
+",
+        )
+        .into_boxed()
+        .bind::<Text, _>(self.stream_name.clone())
+        .bind::<BigInt, _>(lease.from.as_i64())
+        .bind::<Text, _>(self.worker.clone())
+        .bind::<BigInt, _>(self.lease_duration_millis())
+    }
+
+    /// Marks `lease`'s unit as done. Affects no rows when the lease was lost
+    /// to another worker.
+    pub fn complete_query<T: SerialCursor>(&self, lease: &Lease<T>) -> LeaseQuery {
+        diesel::sql_query(
+            r"
+UPDATE diesel_streamer_leases
+SET done = TRUE, lease_expires_at = NULL
+WHERE stream_name = $1 AND unit_start = $2 AND worker = $3 AND NOT done
+",
+        )
+        .into_boxed()
+        .bind::<Text, _>(self.stream_name.clone())
+        .bind::<BigInt, _>(lease.from.as_i64())
+        .bind::<Text, _>(self.worker.clone())
+    }
+
+    /// Counts the units that are not done yet, returning a
+    /// [`RemainingUnits`].
+    pub fn remaining_query(&self) -> LeaseQuery {
+        diesel::sql_query(
+            r"
+SELECT COUNT(*) AS remaining
+FROM diesel_streamer_leases
+WHERE stream_name = $1 AND NOT done
+",
+        )
+        .into_boxed()
+        .bind::<Text, _>(self.stream_name.clone())
+    }
+
+    fn lease_duration_millis(&self) -> i64 {
+        i64::try_from(self.lease_duration.as_millis()).unwrap_or(i64::MAX)
+    }
+}
+
+/// A query of a [`LeaseCoordinator`], with its values bound as parameters.
+pub type LeaseQuery = BoxedSqlQuery<'static, Pg, SqlQuery>;
+
+/// A unit claimed by [`LeaseCoordinator::claim_query`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, QueryableByName)]
+pub struct LeaseRow {
+    #[diesel(sql_type = BigInt)]
+    pub unit_start: i64,
+    #[diesel(sql_type = BigInt)]
+    pub unit_end: i64,
+    #[diesel(sql_type = Integer)]
+    pub attempts: i32,
+}
+
+impl LeaseRow {
+    /// The unit as cursor values, unless they do not fit in `T` or the unit
+    /// is empty.
+    #[must_use]
+    pub fn lease<T: SerialCursor + TryFrom<i64>>(&self) -> Option<Lease<T>> {
+        Some(Lease {
+            from: T::try_from(self.unit_start).ok()?,
+            to: T::try_from(self.unit_end).ok()?.last_below()?,
+            attempts: self.attempts,
+        })
+    }
+}
+
+/// A leased unit, covering the cursor values `from..=to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease<T> {
+    pub from: T,
+    pub to: T,
+    /// How many times the unit was claimed, this time included. Above 1,
+    /// the unit may have been partially streamed by another worker.
+    pub attempts: i32,
+}
+
+/// The number of units of a stream that are not done yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, QueryableByName)]
+pub struct RemainingUnits {
+    #[diesel(sql_type = BigInt)]
+    pub remaining: i64,
+}
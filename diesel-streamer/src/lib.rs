@@ -1,2 +1,123 @@
+/// The async API, enabled by the `async` feature, for `diesel_async`
+/// connections.
+#[cfg(feature = "async")]
+pub mod r#async;
+mod batch_end;
+mod bounds;
+mod cancellation;
+/// The `diesel-streamer` command-line tool, enabled by the `cli` feature.
+#[cfg(feature = "cli")]
+pub mod cli;
+mod copy_table;
+#[cfg(feature = "csv")]
+mod csv_sink;
+mod delete_in_batches;
+mod dry_run;
+#[cfg(feature = "dynamic")]
+mod dynamic_table;
+mod explain;
 mod get_serial_table_async_stream;
+#[doc(hidden)]
+pub mod instrument;
+#[cfg(feature = "json")]
+mod json_sink;
+#[cfg(feature = "postgres")]
+mod lease;
+#[cfg(feature = "parquet")]
+mod parquet_sink;
+mod partitions;
+mod plan_partitions;
+mod progress;
+#[cfg(feature = "arrow")]
+mod record_batch;
+mod serial_cursor;
+mod shard;
+mod stream_chunks;
+mod stream_control;
+mod stream_dynamic_table;
+mod stream_end;
+mod stream_leased_table;
+mod stream_options;
 mod stream_serial_table;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod streamable;
+/// The blocking API, enabled by the `sync` feature, for `diesel`
+/// connections.
+#[cfg(feature = "sync")]
+pub mod sync;
+mod update_in_batches;
+
+#[doc(hidden)]
+pub use batch_end::{__ChunkError, __batch_result};
+pub use batch_end::{BatchEnd, BatchError};
+pub use bounds::Bounds;
+#[doc(hidden)]
+pub use bounds::{__estimate_bounds_query, BoundsEstimate};
+pub use cancellation::Cancellation;
+#[doc(hidden)]
+pub use copy_table::{__ColumnCount, __insert_batch_size};
+#[cfg(all(feature = "csv", feature = "async"))]
+pub use csv_sink::AsyncCsvSink;
+#[cfg(feature = "csv")]
+pub use csv_sink::{CsvSink, CsvSinkBuilder, QuoteStyle};
+#[cfg(feature = "dynamic")]
+#[doc(hidden)]
+pub use dynamic_table::{__narrow_options, __widen_stream_end, CursorType, DynamicCursor};
+#[cfg(feature = "dynamic")]
+pub use dynamic_table::{DynamicColumn, DynamicRow, DynamicTable, DynamicTableError};
+pub use explain::DryRun;
+#[doc(hidden)]
+pub use explain::{__dry_run_report, __explain, Explain};
+#[cfg(all(feature = "json", feature = "async"))]
+pub use json_sink::AsyncJsonLinesSink;
+#[cfg(feature = "json")]
+pub use json_sink::JsonLinesSink;
+#[cfg(feature = "postgres")]
+pub use lease::{
+    diesel_streamer_leases, Lease, LeaseCoordinator, LeaseQuery, LeaseRow, RemainingUnits,
+};
+#[cfg(feature = "parquet")]
+pub use parquet_sink::{ParquetFile, ParquetManifest, ParquetSink};
+#[doc(hidden)]
+pub use partitions::{__partition_bounds_query, __partition_ranges, PartitionBounds};
+pub use progress::Progress;
+#[cfg(feature = "arrow")]
+pub use record_batch::{ArrowColumns, ArrowType, RecordBatchEncoder};
+pub use serial_cursor::SerialCursor;
+#[doc(hidden)]
+pub use shard::__shard_filter;
+pub use shard::{Shard, ShardError, ShardKey};
+pub use stream_control::StreamControl;
+pub use stream_end::StreamEnd;
+pub use stream_options::{OnProgress, StreamOptions, UpperBound};
+
+// The API of the enabled mode is also available from the crate's root. With
+// both, e.g. through feature unification in a workspace, the root stays on
+// the sync API, and the async one is reached through `diesel_streamer::r#async`.
+#[cfg(all(feature = "async", not(feature = "sync")))]
+#[doc(hidden)]
+pub use crate::__async_impl_streamable as __default_impl_streamable;
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+pub use crate::__sync_impl_streamable as __default_impl_streamable;
+#[cfg(all(feature = "async", not(feature = "sync")))]
+pub use crate::r#async::*;
+#[cfg(feature = "sync")]
+pub use crate::sync::*;
+
+#[cfg(feature = "derive")]
+pub use diesel_streamer_derive::Streamable;
+
+#[cfg(feature = "async")]
+#[doc(hidden)]
+pub use stream_chunks::__sleep;
+
+/// Crates named by the async macros' expansions, so that callers need not
+/// depend on them.
+#[cfg(feature = "async")]
+#[doc(hidden)]
+pub mod __private {
+    pub use futures_util;
+    pub use pin_project_lite;
+    pub use tokio;
+}
@@ -0,0 +1,235 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use arrow_schema::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+
+use crate::RecordBatchEncoder;
+
+/// Writes the chunks of a stream to Parquet files in a directory, as one row
+/// group per chunk or per [`chunks_per_row_group`](Self::chunks_per_row_group)
+/// chunks.
+///
+/// Files are named `{file_prefix}-00000.parquet`,
+/// `{file_prefix}-00001.parquet`... and a new one is started once the
+/// current one reaches [`max_rows_per_file`](Self::max_rows_per_file) or
+/// [`max_bytes_per_file`](Self::max_bytes_per_file). [`finish`](Self::finish)
+/// writes a `{file_prefix}-manifest.json` listing every file with its row
+/// count and cursor range.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_serial_table, ParquetSink, RecordBatchEncoder};
+/// use parquet::basic::{Compression, ZstdLevel};
+///
+/// let mut sink = ParquetSink::new("exports/users", RecordBatchEncoder::for_columns(users::all_columns))
+///     .file_prefix("users")
+///     .compression(Compression::ZSTD(ZstdLevel::default()))
+///     .max_rows_per_file(10_000_000);
+///
+/// stream_serial_table!(users, id, conn, |users: Vec<User>| sink.write_chunk(&users, |user| user.id));
+///
+/// let manifest = sink.finish()?;
+/// ```
+#[derive(Debug)]
+pub struct ParquetSink<T> {
+    directory: PathBuf,
+    file_prefix: String,
+    encoder: RecordBatchEncoder,
+    compression: Compression,
+    chunks_per_row_group: usize,
+    max_rows_per_file: Option<usize>,
+    max_bytes_per_file: Option<usize>,
+    writer: Option<ArrowWriter<File>>,
+    chunks_in_row_group: usize,
+    files: Vec<ParquetFile<T>>,
+}
+
+/// A file written by a [`ParquetSink`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParquetFile<T> {
+    /// Name of the file, relative to the sink's directory.
+    pub file_name: String,
+    pub rows: usize,
+    /// Lowest cursor in the file.
+    pub from: T,
+    /// Highest cursor in the file.
+    pub to: T,
+}
+
+/// Lists the files written by a [`ParquetSink`], in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParquetManifest<T> {
+    pub files: Vec<ParquetFile<T>>,
+}
+
+impl<T: Copy + Ord + Serialize> ParquetSink<T> {
+    /// Writes to `directory`, creating it if needed, with the schema of
+    /// `encoder`. Compresses with Snappy by default.
+    pub fn new(directory: impl Into<PathBuf>, encoder: RecordBatchEncoder) -> Self {
+        ParquetSink {
+            directory: directory.into(),
+            file_prefix: "part".to_string(),
+            encoder,
+            compression: Compression::SNAPPY,
+            chunks_per_row_group: 1,
+            max_rows_per_file: None,
+            max_bytes_per_file: None,
+            writer: None,
+            chunks_in_row_group: 0,
+            files: vec![],
+        }
+    }
+
+    /// Names files `{file_prefix}-00000.parquet`... instead of
+    /// `part-00000.parquet`...
+    #[must_use]
+    pub fn file_prefix(mut self, file_prefix: impl Into<String>) -> Self {
+        self.file_prefix = file_prefix.into();
+        self
+    }
+
+    #[must_use]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Groups `chunks_per_row_group` chunks in each row group, however many
+    /// rows they hold.
+    #[must_use]
+    pub fn chunks_per_row_group(mut self, chunks_per_row_group: usize) -> Self {
+        self.chunks_per_row_group = chunks_per_row_group.max(1);
+        self
+    }
+
+    /// Starts a new file once the current one holds `max_rows_per_file` rows.
+    #[must_use]
+    pub fn max_rows_per_file(mut self, max_rows_per_file: usize) -> Self {
+        self.max_rows_per_file = Some(max_rows_per_file);
+        self
+    }
+
+    /// Starts a new file once the current one reaches about
+    /// `max_bytes_per_file` bytes.
+    #[must_use]
+    pub fn max_bytes_per_file(mut self, max_bytes_per_file: usize) -> Self {
+        self.max_bytes_per_file = Some(max_bytes_per_file);
+        self
+    }
+
+    /// Writes a chunk of rows, using `cursor` to read the cursor of each row
+    /// for the manifest.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row does not fit the schema, or when writing fails.
+    pub fn write_chunk<R: Serialize>(
+        &mut self,
+        rows: &[R],
+        cursor: impl Fn(&R) -> T,
+    ) -> Result<(), ParquetError> {
+        let (Some(from), Some(to)) = (
+            rows.iter().map(&cursor).min(),
+            rows.iter().map(&cursor).max(),
+        ) else {
+            return Ok(());
+        };
+
+        let record_batch = self.encoder.encode(rows)?;
+
+        let mut writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => self.create_file(record_batch.schema(), from)?,
+        };
+
+        writer.write(&record_batch)?;
+
+        let file = self.files.last_mut().ok_or_else(|| {
+            ParquetError::General("no file was created for the chunk".to_string())
+        })?;
+        file.rows += rows.len();
+        file.from = file.from.min(from);
+        file.to = file.to.max(to);
+
+        self.chunks_in_row_group += 1;
+
+        if self.chunks_in_row_group >= self.chunks_per_row_group {
+            writer.flush()?;
+            self.chunks_in_row_group = 0;
+        }
+
+        let is_file_full = self
+            .max_rows_per_file
+            .is_some_and(|max_rows_per_file| file.rows >= max_rows_per_file)
+            || self.max_bytes_per_file.is_some_and(|max_bytes_per_file| {
+                writer.bytes_written() + writer.in_progress_size() >= max_bytes_per_file
+            });
+
+        if is_file_full {
+            writer.close()?;
+            self.chunks_in_row_group = 0;
+        } else {
+            self.writer = Some(writer);
+        }
+
+        Ok(())
+    }
+
+    /// Closes the current file and writes the manifest.
+    ///
+    /// # Errors
+    ///
+    /// Fails when writing the last row group or the manifest fails.
+    pub fn finish(mut self) -> Result<ParquetManifest<T>, ParquetError> {
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+
+        let manifest = ParquetManifest { files: self.files };
+
+        fs::create_dir_all(&self.directory)?;
+
+        let manifest_file = File::create(
+            self.directory
+                .join(format!("{}-manifest.json", self.file_prefix)),
+        )?;
+        serde_json::to_writer_pretty(manifest_file, &manifest)
+            .map_err(|error| ParquetError::External(Box::new(error)))?;
+
+        Ok(manifest)
+    }
+
+    fn create_file(
+        &mut self,
+        schema: SchemaRef,
+        from: T,
+    ) -> Result<ArrowWriter<File>, ParquetError> {
+        let file_name = format!("{}-{:05}.parquet", self.file_prefix, self.files.len());
+
+        fs::create_dir_all(&self.directory)?;
+        let file = File::create(self.directory.join(&file_name))?;
+
+        // Row groups are closed by `write_chunk`, after `chunks_per_row_group`
+        // chunks, rather than once they reach parquet's default row count.
+        let properties = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_max_row_group_size(usize::MAX)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema, Some(properties))?;
+
+        self.files.push(ParquetFile {
+            file_name,
+            rows: 0,
+            from,
+            to: from,
+        });
+
+        Ok(writer)
+    }
+}
@@ -0,0 +1,232 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use diesel::backend::Backend;
+use diesel::query_builder::{QueryFragment, SqlQuery};
+use diesel::sql_types::{Nullable, Text};
+use diesel::QueryableByName;
+
+use crate::SerialCursor;
+
+/// Cursor values splitting a table into parts of equal row counts, as read
+/// from table statistics, e.g. `{1,250,1000,40000}`.
+#[doc(hidden)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, QueryableByName)]
+pub struct PartitionBounds {
+    #[diesel(sql_type = Nullable<Text>)]
+    pub bounds: Option<String>,
+}
+
+impl PartitionBounds {
+    /// The bounds as integers, when there are at least two of them.
+    fn values(&self) -> Option<Vec<i64>> {
+        let bounds = self.bounds.as_deref()?;
+        let bounds = bounds.strip_prefix('{')?.strip_suffix('}')?;
+
+        let values = bounds
+            .split(',')
+            .map(|value| value.trim().parse().ok())
+            .collect::<Option<Vec<i64>>>()?;
+
+        if values.len() < 2 {
+            None
+        } else {
+            Some(values)
+        }
+    }
+}
+
+/// The query reading the bounds splitting `cursor_field`'s table into
+/// `partitions` parts, if `DB` has statistics to read them from:
+///
+/// - without `sample_percent`, the column's `pg_stats.histogram_bounds`
+/// - with `sample_percent`, a `percentile_disc` over a `TABLESAMPLE` of the
+///   table
+#[doc(hidden)]
+pub fn __partition_bounds_query<DB, C>(
+    cursor_field: &C,
+    _backend: PhantomData<DB>,
+    partitions: usize,
+    sample_percent: Option<f64>,
+) -> Option<SqlQuery>
+where
+    DB: Backend + Default + 'static,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    let (table_identifiers, column) = crate::bounds::cursor_identifiers(cursor_field)?;
+
+    partition_bounds_query::<DB>(&table_identifiers, &column, partitions, sample_percent)
+}
+
+#[cfg(feature = "postgres")]
+fn partition_bounds_query<DB: 'static>(
+    table_identifiers: &[String],
+    column: &str,
+    partitions: usize,
+    sample_percent: Option<f64>,
+) -> Option<SqlQuery> {
+    use crate::bounds::{quote_identifiers, quote_literal};
+
+    if std::any::TypeId::of::<DB>() != std::any::TypeId::of::<diesel::pg::Pg>() {
+        return None;
+    }
+
+    let table = quote_identifiers(table_identifiers);
+
+    let query = match sample_percent {
+        Some(sample_percent) if sample_percent > 0.0 && sample_percent <= 100.0 => {
+            postgres_sampled_bounds(&table, column, partitions, sample_percent)
+        }
+        Some(_) => return None,
+        None => postgres_histogram_bounds(&table, &quote_literal(column)),
+    };
+
+    Some(diesel::sql_query(query))
+}
+
+#[cfg(not(feature = "postgres"))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn partition_bounds_query<DB>(
+    _table_identifiers: &[String],
+    _column: &str,
+    _partitions: usize,
+    _sample_percent: Option<f64>,
+) -> Option<SqlQuery> {
+    None
+}
+
+/// Each pair of consecutive histogram bounds holds about as many rows as
+/// the others. Returns no rows when the column was never analyzed.
+#[cfg(feature = "postgres")]
+fn postgres_histogram_bounds(table: &str, column: &str) -> String {
+    format!(
+        r"
+SELECT histogram_bounds::TEXT AS bounds
+FROM pg_stats
+WHERE (quote_ident(schemaname) || '.' || quote_ident(tablename))::REGCLASS = {table}::REGCLASS
+    AND attname = {column}
+    AND histogram_bounds::TEXT ~ '^\{{-?[0-9]+(,-?[0-9]+)*\}}$'
+ORDER BY inherited
+LIMIT 1
+",
+        table = crate::bounds::quote_literal(table),
+        column = column,
+    )
+}
+
+#[cfg(feature = "postgres")]
+#[allow(clippy::cast_precision_loss)]
+fn postgres_sampled_bounds(
+    table: &str,
+    column: &str,
+    partitions: usize,
+    sample_percent: f64,
+) -> String {
+    let fractions = (0..=partitions)
+        .map(|partition| (partition as f64 / partitions as f64).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        r"
+SELECT (
+    percentile_disc(ARRAY[{fractions}]::FLOAT8[]) WITHIN GROUP (ORDER BY {column})
+)::TEXT AS bounds
+FROM {table} TABLESAMPLE BERNOULLI ({sample_percent})
+",
+        fractions = fractions,
+        column = crate::bounds::quote_identifiers(&[column.to_owned()]),
+        table = table,
+        sample_percent = sample_percent,
+    )
+}
+
+/// Splits `lower..=upper` into at most `partitions` contiguous ranges.
+///
+/// With `bounds`, the ranges hold about as many rows as each other.
+/// Without, they are about as wide as each other.
+#[doc(hidden)]
+#[must_use]
+pub fn __partition_ranges<T>(
+    lower: T,
+    upper: T,
+    partitions: usize,
+    bounds: Option<&PartitionBounds>,
+) -> Vec<Range<T>>
+where
+    T: SerialCursor + TryFrom<i64>,
+{
+    if upper < lower {
+        return vec![];
+    }
+
+    let lower_value = i128::from(lower.as_i64());
+    let end_value = i128::from(upper.as_i64()) + 1;
+    let partitions = partitions.max(1);
+
+    let mut splits = match bounds.and_then(PartitionBounds::values) {
+        Some(values) => balanced_splits(&values, partitions),
+        None => equal_width_splits(lower_value, end_value, partitions),
+    };
+
+    splits.retain(|&split| lower_value < split && split < end_value);
+    splits.sort_unstable();
+    splits.dedup();
+
+    let mut starts = vec![lower];
+    starts.extend(
+        splits
+            .into_iter()
+            .filter_map(|split| i64::try_from(split).ok())
+            .filter_map(|split| T::try_from(split).ok()),
+    );
+
+    // `upper + 1` may not fit in `T`, in which case the last range ends at
+    // `upper`, the type's maximum, which no range can cover
+    let end = i64::try_from(end_value)
+        .ok()
+        .and_then(|end| T::try_from(end).ok());
+
+    let mut ranges = starts
+        .windows(2)
+        .map(|window| window[0]..window[1])
+        .collect::<Vec<_>>();
+
+    if let Some(&last_start) = starts.last() {
+        ranges.push(last_start..end.unwrap_or(upper));
+    }
+
+    ranges
+}
+
+/// The splits leaving as many of `values`' buckets to each partition,
+/// interpolating inside a bucket when needed.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn balanced_splits(values: &[i64], partitions: usize) -> Vec<i128> {
+    let buckets = (values.len() - 1) as f64;
+
+    (1..partitions)
+        .map(|partition| {
+            let position = buckets * partition as f64 / partitions as f64;
+            let bucket = position.floor() as usize;
+            let start = values[bucket] as f64;
+            let end = values.get(bucket + 1).map_or(start, |&end| end as f64);
+
+            (start + (end - start) * position.fract()).round() as i128
+        })
+        .collect()
+}
+
+fn equal_width_splits(lower: i128, end: i128, partitions: usize) -> Vec<i128> {
+    let partitions = partitions as i128;
+
+    (1..partitions)
+        .map(|partition| lower + (end - lower) * partition / partitions)
+        .collect()
+}
@@ -0,0 +1,177 @@
+/// Plans cursor ranges holding about as many rows as each other, to stream a
+/// skewed table in chunks of even sizes rather than of even widths.
+///
+/// Takes the table (or a filtered query on it), its serial field, a
+/// connection and the number of partitions. Bounds splitting the rows into
+/// even parts are read from the cursor field's statistics:
+///
+/// - by default, its Postgres `pg_stats.histogram_bounds`, as of the last
+///   `ANALYZE`
+/// - with `sample_percent = ...`, a `percentile_disc` over a
+///   `TABLESAMPLE BERNOULLI` of that percentage of the table
+///
+/// Statistics cover the whole table, ignoring the query's filters. When
+/// there are none to read, e.g. on another backend, or they cannot be read,
+/// the ranges are as wide as each other instead. They are read under a
+/// savepoint, so a failure never aborts the caller's transaction.
+///
+/// Evaluates to a `Vec<Range<_>>` covering `MIN(cursor)..=MAX(cursor)` of
+/// the query, or up to `MAX(cursor)` excluded when it is the cursor type's
+/// maximum, empty when it has no rows, to be handed to
+/// [`StreamOptions::partitions`](crate::StreamOptions::partitions). Its
+/// cursor type can only be inferred from there, so annotate it otherwise,
+/// e.g. `let partitions: Vec<Range<i64>> = plan_partitions!(...)`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{plan_partitions, stream_serial_table, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::events::dsl::{events, id};
+///
+///     let mut conn = pool.get().await.unwrap();
+///
+///     let partitions = plan_partitions!(events, id, conn, partitions = 100);
+///     // or, without relying on the last ANALYZE
+///     let partitions = plan_partitions!(events, id, conn, partitions = 100, sample_percent = 1.0);
+///
+///     stream_serial_table!(events, id, conn, options = StreamOptions::new().partitions(partitions), |events: Vec<Event>| async {
+///         // each chunk holds about 1% of the events
+///     });
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_plan_partitions {
+    ( $query:expr , $cursor_field:expr , $conn:expr , partitions = $partitions:expr $(, sample_percent = $sample_percent:expr)? $(,)? ) => {{
+        use diesel_async::RunQueryDsl;
+
+        fn backend_of<C: diesel_async::AsyncConnection>(
+            _conn: &C,
+        ) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        diesel_streamer::__plan_partitions!(.await;
+            $query,
+            $cursor_field,
+            $conn,
+            $partitions,
+            None $(.or(Some($sample_percent)))?
+        )
+    }};
+}
+
+/// Plans cursor ranges holding about as many rows as each other, to stream a
+/// skewed table in chunks of even sizes rather than of even widths.
+///
+/// Takes the table (or a filtered query on it), its serial field, a
+/// connection and the number of partitions. Bounds splitting the rows into
+/// even parts are read from the cursor field's statistics:
+///
+/// - by default, its Postgres `pg_stats.histogram_bounds`, as of the last
+///   `ANALYZE`
+/// - with `sample_percent = ...`, a `percentile_disc` over a
+///   `TABLESAMPLE BERNOULLI` of that percentage of the table
+///
+/// Statistics cover the whole table, ignoring the query's filters. When
+/// there are none to read, e.g. on another backend, or they cannot be read,
+/// the ranges are as wide as each other instead. They are read under a
+/// savepoint, so a failure never aborts the caller's transaction.
+///
+/// Evaluates to a `Vec<Range<_>>` covering `MIN(cursor)..=MAX(cursor)` of
+/// the query, or up to `MAX(cursor)` excluded when it is the cursor type's
+/// maximum, empty when it has no rows, to be handed to
+/// [`StreamOptions::partitions`](crate::StreamOptions::partitions). Its
+/// cursor type can only be inferred from there, so annotate it otherwise,
+/// e.g. `let partitions: Vec<Range<i64>> = plan_partitions!(...)`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{plan_partitions, stream_serial_table, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::events::dsl::{events, id};
+///
+///     let mut conn = pool.get().unwrap();
+///
+///     let partitions = plan_partitions!(events, id, conn, partitions = 100);
+///     // or, without relying on the last ANALYZE
+///     let partitions = plan_partitions!(events, id, conn, partitions = 100, sample_percent = 1.0);
+///
+///     stream_serial_table!(events, id, conn, options = StreamOptions::new().partitions(partitions), |events: Vec<Event>| {
+///         // each chunk holds about 1% of the events
+///     });
+/// }
+/// ```
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_plan_partitions {
+    ( $query:expr , $cursor_field:expr , $conn:expr , partitions = $partitions:expr $(, sample_percent = $sample_percent:expr)? $(,)? ) => {{
+        use diesel::RunQueryDsl;
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        diesel_streamer::__plan_partitions!(;
+            $query,
+            $cursor_field,
+            $conn,
+            $partitions,
+            None $(.or(Some($sample_percent)))?
+        )
+    }};
+}
+
+/// Reads the bounds of the query and the statistics of its cursor field on
+/// behalf of `plan_partitions!`.
+///
+/// Prefix the arguments with `.await;` in async mode and `;` in sync mode.
+/// The caller brings the matching `RunQueryDsl` into scope, along with a
+/// `backend_of` function returning the `PhantomData` of `$conn`'s backend.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __plan_partitions {
+    ( $(.$await:tt)? ; $query:expr , $cursor_field:expr , $conn:expr , $partitions:expr , $sample_percent:expr ) => {{
+        use diesel::dsl::{max, min};
+        use diesel::QueryDsl;
+
+        let partitions: usize = $partitions;
+        let sample_percent: Option<f64> = $sample_percent;
+
+        let (lower, upper) = $query
+            .select((min($cursor_field), max($cursor_field)))
+            .get_result::<(Option<_>, Option<_>)>($conn)
+            $(.$await)?
+            .unwrap();
+
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => {
+                let bounds_query = diesel_streamer::__partition_bounds_query(
+                    &$cursor_field,
+                    backend_of(&*$conn),
+                    partitions,
+                    sample_percent,
+                );
+                let bounds = match bounds_query {
+                    Some(bounds_query) => diesel_streamer::__get_result_in_savepoint!($(.$await)?;
+                        bounds_query,
+                        diesel_streamer::PartitionBounds,
+                        $conn,
+                        "diesel_streamer_partitions"
+                    )
+                    .ok(),
+                    None => None,
+                };
+
+                diesel_streamer::__partition_ranges(lower, upper, partitions, bounds.as_ref())
+            }
+            _ => vec![],
+        }
+    }};
+}
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use crate::SerialCursor;
+
+/// A snapshot of how far a stream has gotten, reported after each chunk.
+///
+/// See [`StreamOptions::on_progress`](crate::StreamOptions::on_progress).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Progress<T> {
+    /// Where the next chunk starts. Every row below it has been processed.
+    pub cursor: T,
+    /// The cursor's beginning.
+    pub from: T,
+    /// The cursor's end. It moves with a moving upper bound.
+    pub to: T,
+    /// Rows streamed so far.
+    pub rows: usize,
+    /// Chunks streamed so far.
+    pub chunks: usize,
+    /// Time since the stream started.
+    pub elapsed: Duration,
+    started_at: Instant,
+}
+
+impl<T: Copy> Progress<T> {
+    #[must_use]
+    pub fn start(from: T, to: T) -> Self {
+        Progress {
+            cursor: from,
+            from,
+            to,
+            rows: 0,
+            chunks: 0,
+            elapsed: Duration::ZERO,
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn record_chunk(&mut self, cursor: T, rows: usize) {
+        self.cursor = cursor;
+        self.rows += rows;
+        self.chunks += 1;
+        self.elapsed = self.started_at.elapsed();
+    }
+
+    /// The same snapshot, with cursor values converted by `convert`.
+    #[cfg(feature = "dynamic")]
+    pub(crate) fn map<U>(&self, convert: impl Fn(T) -> U) -> Progress<U> {
+        Progress {
+            cursor: convert(self.cursor),
+            from: convert(self.from),
+            to: convert(self.to),
+            rows: self.rows,
+            chunks: self.chunks,
+            elapsed: self.elapsed,
+            started_at: self.started_at,
+        }
+    }
+}
+
+impl<T: SerialCursor> Progress<T> {
+    /// How far the cursor has moved through `from..=to`, from `0.0` to `100.0`.
+    #[must_use]
+    pub fn percent_complete(&self) -> f64 {
+        let total = self.to.as_f64() + 1.0 - self.from.as_f64();
+
+        if total <= 0.0 {
+            return 100.0;
+        }
+
+        let done = self.cursor.as_f64() - self.from.as_f64();
+
+        (done / total * 100.0).clamp(0.0, 100.0)
+    }
+
+    /// Estimated time left, assuming the remaining cursor range is streamed
+    /// at the pace seen so far. `None` until the first chunk is done.
+    #[must_use]
+    pub fn eta(&self) -> Option<Duration> {
+        let percent_complete = self.percent_complete();
+
+        if percent_complete <= 0.0 {
+            return None;
+        }
+
+        let remaining = self.elapsed.as_secs_f64() * (100.0 - percent_complete) / percent_complete;
+
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::UInt8Type;
+use arrow_array::{Array, ArrayRef, BinaryArray, RecordBatch};
+use arrow_json::reader::{infer_json_schema_from_iterator, ReaderBuilder};
+use arrow_schema::{ArrowError, DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use diesel::{sql_types, Column, Expression};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Serialize;
+
+/// Turns the chunks of a stream into Arrow [`RecordBatch`]es, e.g. to feed
+/// DataFusion or Polars.
+///
+/// Rows may be any `T: Serialize`, whose fields are matched by name with the
+/// fields of the schema. The schema comes either from diesel columns with
+/// [`for_columns`](Self::for_columns), or is inferred from the first
+/// non-empty chunk with [`infer`](Self::infer).
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_serial_table, RecordBatchEncoder};
+///
+/// let mut encoder = RecordBatchEncoder::for_columns(users::all_columns);
+///
+/// stream_serial_table!(users, id, conn, |users: Vec<User>| {
+///     let record_batch = encoder.encode(&users).unwrap();
+///
+///     // hand over to DataFusion, Polars...
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordBatchEncoder {
+    schema: Option<SchemaRef>,
+}
+
+impl RecordBatchEncoder {
+    /// Encodes rows with `schema`.
+    pub fn new(schema: impl Into<SchemaRef>) -> Self {
+        RecordBatchEncoder {
+            schema: Some(schema.into()),
+        }
+    }
+
+    /// Encodes rows with the schema of diesel `columns`, such as a table's
+    /// `all_columns` or a tuple of the selected columns.
+    pub fn for_columns<C: ArrowColumns>(_columns: C) -> Self {
+        Self::new(C::schema())
+    }
+
+    /// Encodes rows with the schema inferred from the first non-empty chunk.
+    /// Fields keep the order in which rows serialize them, and are all
+    /// nullable.
+    #[must_use]
+    pub fn infer() -> Self {
+        RecordBatchEncoder { schema: None }
+    }
+
+    /// The schema of the record batches, unless it is yet to be inferred.
+    #[must_use]
+    pub fn schema(&self) -> Option<SchemaRef> {
+        self.schema.clone()
+    }
+
+    /// Encodes a chunk of rows into a record batch.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a row does not fit the schema.
+    pub fn encode<T: Serialize>(&mut self, rows: &[T]) -> Result<RecordBatch, ArrowError> {
+        let schema = match (&self.schema, rows.first()) {
+            (Some(schema), _) => schema.clone(),
+            (None, Some(first_row)) => self
+                .schema
+                .insert(Arc::new(infer_schema(first_row, rows)?))
+                .clone(),
+            (None, None) => return Ok(RecordBatch::new_empty(Arc::new(Schema::empty()))),
+        };
+
+        let mut decoder = ReaderBuilder::new(json_schema(&schema)).build_decoder()?;
+        decoder.serialize(rows)?;
+
+        match decoder.flush()? {
+            Some(record_batch) => with_binary_columns(&record_batch, schema),
+            None => Ok(RecordBatch::new_empty(schema)),
+        }
+    }
+}
+
+/// arrow-json cannot decode `Binary` fields, so they are decoded as the lists
+/// of bytes that serde serializes a `Vec<u8>` into instead.
+fn json_schema(schema: &SchemaRef) -> SchemaRef {
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| field.data_type() == &DataType::Binary)
+    {
+        return schema.clone();
+    }
+
+    let fields: Fields = schema
+        .fields()
+        .iter()
+        .map(|field| match field.data_type() {
+            DataType::Binary => Arc::new(
+                field
+                    .as_ref()
+                    .clone()
+                    .with_data_type(DataType::new_list(DataType::UInt8, false)),
+            ),
+            _ => field.clone(),
+        })
+        .collect();
+
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Turns the lists of bytes decoded for the `Binary` fields of `schema` back
+/// into binary columns.
+fn with_binary_columns(
+    record_batch: &RecordBatch,
+    schema: SchemaRef,
+) -> Result<RecordBatch, ArrowError> {
+    let columns = record_batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| match field.data_type() {
+            DataType::Binary => {
+                let bytes = column.as_list::<i32>();
+
+                Arc::new(BinaryArray::new(
+                    bytes.offsets().clone(),
+                    bytes
+                        .values()
+                        .as_primitive::<UInt8Type>()
+                        .values()
+                        .inner()
+                        .clone(),
+                    bytes.nulls().cloned(),
+                )) as ArrayRef
+            }
+            _ => column.clone(),
+        })
+        .collect();
+
+    RecordBatch::try_new(schema, columns)
+}
+
+fn infer_schema<T: Serialize>(first_row: &T, rows: &[T]) -> Result<Schema, ArrowError> {
+    let field_names: FieldNames = serde_json::to_string(first_row)
+        .and_then(|first_row| serde_json::from_str(&first_row))
+        .map_err(|error| ArrowError::JsonError(error.to_string()))?;
+
+    let inferred_schema = infer_json_schema_from_iterator(rows.iter().map(|row| {
+        serde_json::to_value(row).map_err(|error| ArrowError::JsonError(error.to_string()))
+    }))?;
+
+    let mut fields: Vec<_> = inferred_schema.fields().iter().cloned().collect();
+    fields.sort_by_key(|field| {
+        field_names
+            .0
+            .iter()
+            .position(|field_name| field_name == field.name())
+    });
+
+    Ok(Schema::new(fields))
+}
+
+/// The field names of a row serialized as a map, in serialization order.
+struct FieldNames(Vec<String>);
+
+impl<'de> Deserialize<'de> for FieldNames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldNamesVisitor;
+
+        impl<'de> Visitor<'de> for FieldNamesVisitor {
+            type Value = FieldNames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a row serialized as a map")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldNames, A::Error> {
+                let mut field_names = vec![];
+
+                while let Some((field_name, IgnoredAny)) = map.next_entry::<String, IgnoredAny>()? {
+                    field_names.push(field_name);
+                }
+
+                Ok(FieldNames(field_names))
+            }
+        }
+
+        deserializer.deserialize_map(FieldNamesVisitor)
+    }
+}
+
+/// A diesel SQL type with an Arrow counterpart.
+pub trait ArrowType {
+    fn data_type() -> DataType;
+
+    #[must_use]
+    fn is_nullable() -> bool {
+        false
+    }
+}
+
+macro_rules! impl_arrow_type {
+    ( $($sql_type:ty => $data_type:expr),+ $(,)? ) => {
+        $(
+            impl ArrowType for $sql_type {
+                fn data_type() -> DataType {
+                    $data_type
+                }
+            }
+        )+
+    };
+}
+
+impl_arrow_type! {
+    sql_types::Bool => DataType::Boolean,
+    sql_types::SmallInt => DataType::Int16,
+    sql_types::Integer => DataType::Int32,
+    sql_types::BigInt => DataType::Int64,
+    sql_types::Float => DataType::Float32,
+    sql_types::Double => DataType::Float64,
+    sql_types::Text => DataType::Utf8,
+    sql_types::Binary => DataType::Binary,
+    sql_types::Date => DataType::Date32,
+    sql_types::Time => DataType::Time64(TimeUnit::Microsecond),
+    sql_types::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+}
+
+impl<T: ArrowType> ArrowType for sql_types::Nullable<T> {
+    fn data_type() -> DataType {
+        T::data_type()
+    }
+
+    fn is_nullable() -> bool {
+        true
+    }
+}
+
+/// A tuple of diesel columns whose SQL types have Arrow counterparts, such
+/// as a table's `all_columns`.
+pub trait ArrowColumns {
+    fn schema() -> Schema;
+}
+
+macro_rules! impl_arrow_columns {
+    ( $column:ident ) => {
+        impl_arrow_columns!(@impl $column);
+    };
+
+    ( $column:ident, $($rest:ident),+ ) => {
+        impl_arrow_columns!(@impl $column, $($rest),+);
+        impl_arrow_columns!($($rest),+);
+    };
+
+    ( @impl $($column:ident),+ ) => {
+        impl<$($column),+> ArrowColumns for ($($column,)+)
+        where
+            $($column: Column, <$column as Expression>::SqlType: ArrowType,)+
+        {
+            fn schema() -> Schema {
+                Schema::new(vec![$(
+                    Field::new(
+                        <$column as Column>::NAME,
+                        <<$column as Expression>::SqlType as ArrowType>::data_type(),
+                        <<$column as Expression>::SqlType as ArrowType>::is_nullable(),
+                    )
+                ),+])
+            }
+        }
+    };
+}
+
+impl_arrow_columns!(C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15, C16);
@@ -0,0 +1,58 @@
+/// Integer types usable as the cursor of a serial table.
+///
+/// Implemented for the Rust types of diesel's `SmallSerial`, `Serial` and
+/// `BigSerial` columns.
+pub trait SerialCursor: Copy + PartialOrd {
+    /// Converts the cursor into an `f64`, used for progress estimates.
+    fn as_f64(self) -> f64;
+
+    /// Converts the cursor into an `i64`, used to plan partitions.
+    fn as_i64(self) -> i64;
+
+    /// The cursor following `self`, or `None` at the type's maximum, past
+    /// which no row can be left to stream.
+    fn checked_next(self) -> Option<Self>;
+
+    /// The last cursor of the chunk of `chunk_size` values starting at
+    /// `self`, saturating at the type's maximum. A chunk holds at least
+    /// `self`.
+    #[must_use]
+    fn chunk_end(self, chunk_size: Self) -> Self;
+
+    /// The last cursor of a range ending at `self`, exclusive, or `None` at
+    /// the type's minimum, where such a range holds no cursor.
+    #[must_use]
+    fn last_below(self) -> Option<Self>;
+}
+
+macro_rules! impl_serial_cursor {
+    ($($cursor_type:ty),*) => {
+        $(
+            impl SerialCursor for $cursor_type {
+                #[allow(clippy::cast_precision_loss, clippy::cast_lossless)]
+                fn as_f64(self) -> f64 {
+                    self as f64
+                }
+
+                #[allow(clippy::cast_lossless)]
+                fn as_i64(self) -> i64 {
+                    self as i64
+                }
+
+                fn checked_next(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn chunk_end(self, chunk_size: Self) -> Self {
+                    self.saturating_add(chunk_size.max(1) - 1)
+                }
+
+                fn last_below(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+            }
+        )*
+    };
+}
+
+impl_serial_cursor!(i16, i32, i64);
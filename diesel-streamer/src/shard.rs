@@ -0,0 +1,215 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use diesel::backend::Backend;
+use diesel::expression::SqlLiteral;
+use diesel::query_builder::QueryFragment;
+use diesel::sql_types::Bool;
+
+/// Restricts a stream to the rows of worker `index` out of `count`, so a
+/// fixed set of workers, e.g. the pods of a StatefulSet, split a table
+/// deterministically without coordinating.
+///
+/// Worker `k` of `n` only streams the rows whose cursor satisfies
+/// `cursor % n = k`, or, with a [hashed key](Self::hashed_on), whose
+/// `hashtext(key) % n = k`. Every worker still walks the whole cursor range,
+/// chunk by chunk.
+///
+/// # Examples
+///
+/// ```
+/// use diesel_streamer::{Shard, StreamOptions};
+///
+/// let options = StreamOptions::<i32>::new().shard(Shard::new(1, 3));
+///
+/// assert_eq!(options.shard, Some(Shard::new(1, 3)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+    pub key: ShardKey,
+}
+
+/// What the rows are assigned to shards by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardKey {
+    /// The cursor's value.
+    Cursor,
+    /// Postgres' `hashtext` of the named column of the cursor's table, for
+    /// keys that are not integers.
+    Hashed(String),
+}
+
+/// Why a [`Shard`] could not be read from the environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardError {
+    /// The variable is neither set nor derivable.
+    Missing(&'static str),
+    /// The variable is not a non-negative integer.
+    Invalid(&'static str, String),
+    /// The index is not below the count.
+    OutOfRange { index: u32, count: u32 },
+}
+
+impl fmt::Display for ShardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardError::Missing(variable) => write!(f, "{variable} is not set"),
+            ShardError::Invalid(variable, value) => {
+                write!(f, "{variable} is not a valid shard number: {value:?}")
+            }
+            ShardError::OutOfRange { index, count } => {
+                write!(f, "shard index {index} is out of {count} shards")
+            }
+        }
+    }
+}
+
+impl Error for ShardError {}
+
+impl Shard {
+    /// The variable holding the number of shards.
+    pub const COUNT_VARIABLE: &'static str = "DIESEL_STREAMER_SHARD_COUNT";
+    /// The variable holding the index of this worker's shard.
+    pub const INDEX_VARIABLE: &'static str = "DIESEL_STREAMER_SHARD_INDEX";
+
+    /// Worker `index` of `count`, sharding by the cursor's value.
+    ///
+    /// # Panics
+    ///
+    /// When `index` is not below `count`.
+    #[must_use]
+    pub fn new(index: u32, count: u32) -> Self {
+        assert!(
+            index < count,
+            "shard index {index} is out of {count} shards"
+        );
+
+        Shard {
+            index,
+            count,
+            key: ShardKey::Cursor,
+        }
+    }
+
+    /// Reads the shard from `DIESEL_STREAMER_SHARD_COUNT` and
+    /// `DIESEL_STREAMER_SHARD_INDEX`. Without an index, it is read from the
+    /// ordinal suffix of `HOSTNAME`, as set on the pods of a StatefulSet,
+    /// e.g. 3 for `backfill-3`.
+    ///
+    /// # Errors
+    ///
+    /// When a variable is missing, is not a number, or the index is not
+    /// below the count.
+    pub fn from_env() -> Result<Self, ShardError> {
+        let count = read_variable(Self::COUNT_VARIABLE)?;
+        let index = if let Ok(index) = env::var(Self::INDEX_VARIABLE) {
+            parse_number(Self::INDEX_VARIABLE, &index)?
+        } else {
+            let hostname =
+                env::var("HOSTNAME").map_err(|_| ShardError::Missing(Self::INDEX_VARIABLE))?;
+            let ordinal = hostname
+                .rsplit('-')
+                .next()
+                .ok_or(ShardError::Missing(Self::INDEX_VARIABLE))?;
+
+            parse_number("HOSTNAME", ordinal)?
+        };
+
+        if index < count {
+            Ok(Shard::new(index, count))
+        } else {
+            Err(ShardError::OutOfRange { index, count })
+        }
+    }
+
+    /// Assigns rows to shards by Postgres' `hashtext` of `column` instead of
+    /// the cursor's value, e.g. for UUID or text keys.
+    #[must_use]
+    pub fn hashed_on(mut self, column: impl Into<String>) -> Self {
+        self.key = ShardKey::Hashed(column.into());
+        self
+    }
+}
+
+fn read_variable(variable: &'static str) -> Result<u32, ShardError> {
+    let value = env::var(variable).map_err(|_| ShardError::Missing(variable))?;
+
+    parse_number(variable, &value)
+}
+
+fn parse_number(variable: &'static str, value: &str) -> Result<u32, ShardError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ShardError::Invalid(variable, value.to_owned()))
+}
+
+/// The predicate keeping the rows of `shard`, to filter each chunk with.
+///
+/// # Panics
+///
+/// When `shard` is hashed on another backend than Postgres.
+#[doc(hidden)]
+pub fn __shard_filter<DB, C>(
+    cursor_field: &C,
+    _backend: PhantomData<DB>,
+    shard: &Shard,
+) -> SqlLiteral<Bool>
+where
+    DB: Backend + Default + 'static,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    let key = match &shard.key {
+        ShardKey::Cursor => {
+            use diesel::query_builder::QueryBuilder;
+
+            let mut query_builder = DB::QueryBuilder::default();
+            cursor_field
+                .to_sql(&mut query_builder, &DB::default())
+                .expect("the cursor field could not be rendered");
+
+            query_builder.finish()
+        }
+        ShardKey::Hashed(column) => hashed_key::<DB, C>(cursor_field, column)
+            .expect("hashed shards are only supported on Postgres"),
+    };
+
+    // `%` keeps the sign of negative keys, hence the extra `+ count`
+    diesel::dsl::sql(&format!(
+        "(({key} % {count}) + {count}) % {count} = {index}",
+        key = key,
+        count = shard.count,
+        index = shard.index,
+    ))
+}
+
+#[cfg(feature = "postgres")]
+fn hashed_key<DB, C>(cursor_field: &C, column: &str) -> Option<String>
+where
+    DB: Backend + Default + 'static,
+    DB::QueryBuilder: Default,
+    C: QueryFragment<DB>,
+{
+    if std::any::TypeId::of::<DB>() != std::any::TypeId::of::<diesel::pg::Pg>() {
+        return None;
+    }
+
+    let (mut identifiers, _) = crate::bounds::cursor_identifiers(cursor_field)?;
+    identifiers.push(column.to_owned());
+
+    Some(format!(
+        "hashtext(CAST({} AS TEXT))",
+        crate::bounds::quote_identifiers(&identifiers)
+    ))
+}
+
+#[cfg(not(feature = "postgres"))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn hashed_key<DB, C>(_cursor_field: &C, _column: &str) -> Option<String> {
+    None
+}
@@ -0,0 +1,242 @@
+/// Walks a serial table chunk by chunk on behalf of the public macros, which
+/// provide the work done for each chunk.
+///
+/// Resolves the cursor's bounds from `$options`, then evaluates `$chunk_body`
+/// for every chunk `$from..=$chunk_to`, following `$options.partitions`
+/// when set, and taking care of the moving upper bound, pauses,
+/// cancellation, progress and instrumentation. `$chunk_body` evaluates to a
+/// `ControlFlow` that either continues with the number of rows in the chunk
+/// or breaks to stop the stream. Evaluates to a `StreamEnd`.
+///
+/// Prefix the arguments with `.await;` in async mode and `;` in sync mode.
+/// The caller brings the matching `RunQueryDsl` into scope, along with a
+/// `backend_of` function returning the `PhantomData` of `$conn`'s backend.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stream_chunks {
+    ( $(.$await:tt)? ; $query:expr , $cursor_field:expr , $conn:expr , $options:ident , $default_chunk_size:expr , |$from:ident, $chunk_to:ident, $chunk:ident| $chunk_body:expr ) => {{
+        use diesel::dsl::max;
+        use diesel::QueryDsl;
+
+        let instrument =
+            diesel_streamer::instrument::StreamInstrument::start($options.name.as_deref());
+        let chunk_size = $options.chunk_size.unwrap_or($default_chunk_size);
+
+        let bounds = diesel_streamer::__resolve_bounds!($(.$await)?;
+            $query,
+            $cursor_field,
+            $conn,
+            $options,
+            instrument
+        );
+
+        if let (Some(mut $from), Some(mut to)) = bounds {
+            instrument.record_bounds($from, to, chunk_size);
+
+            let mut upper_bound_refreshes = 0;
+            let mut progress = diesel_streamer::Progress::start($from, to);
+            // Set once the chunk ending at the cursor type's maximum is
+            // processed, as no cursor follows it
+            let mut exhausted = false;
+
+            'stream: loop {
+                while !exhausted && $from <= to {
+                    if let Some(pause) = $options.pause.filter(|_| progress.chunks > 0) {
+                        diesel_streamer::__pause!($(.$await)?; pause);
+                    }
+
+                    if $options.is_cancelled() {
+                        instrument.record_cancellation($from);
+
+                        break 'stream diesel_streamer::StreamEnd::Cancelled {
+                            cursor: Some($from),
+                        };
+                    }
+
+                    let $chunk_to = std::cmp::min(
+                        match $options.partition_end($from) {
+                            // Past `$from`, so never the type's minimum
+                            Some(partition_end) => {
+                                diesel_streamer::SerialCursor::last_below(partition_end).unwrap_or($from)
+                            }
+                            None => diesel_streamer::SerialCursor::chunk_end($from, chunk_size),
+                        },
+                        to,
+                    );
+                    let mut $chunk = instrument.chunk($from, $chunk_to);
+
+                    match $chunk_body {
+                        std::ops::ControlFlow::Continue(rows) => {
+                            match diesel_streamer::SerialCursor::checked_next($chunk_to) {
+                                Some(next) => $from = next,
+                                None => {
+                                    $from = $chunk_to;
+                                    exhausted = true;
+                                }
+                            }
+
+                            progress.record_chunk($from, rows);
+                            $options.report_progress(&progress);
+                        }
+                        std::ops::ControlFlow::Break(reason) => {
+                            instrument.record_stop($from);
+
+                            break 'stream diesel_streamer::StreamEnd::Stopped {
+                                cursor: Some($from),
+                                reason,
+                            };
+                        }
+                    }
+                }
+
+                if exhausted {
+                    // No cursor is left to resume from
+                    break diesel_streamer::StreamEnd::Completed { cursor: None };
+                }
+
+                if !$options.should_refresh_upper_bound(upper_bound_refreshes) {
+                    break diesel_streamer::StreamEnd::Completed { cursor: Some($from) };
+                }
+
+                let refreshed_to = $query
+                    .select(max($cursor_field))
+                    .get_result::<Option<_>>($conn)
+                    $(.$await)?
+                    .unwrap();
+
+                match refreshed_to {
+                    Some(refreshed_to) if refreshed_to > to => {
+                        to = refreshed_to;
+                        instrument.record_upper_bound_refresh(to);
+                        progress.to = to;
+                        upper_bound_refreshes += 1;
+                    }
+                    _ => break diesel_streamer::StreamEnd::Completed { cursor: Some($from) },
+                }
+            }
+        } else {
+            diesel_streamer::StreamEnd::Completed { cursor: None }
+        }
+    }};
+}
+
+/// Loads the result of a statistics query, such as a bounds estimate, under
+/// the savepoint `$savepoint`: a failing statement aborts the caller's
+/// transaction, if any. Outside of a transaction, Postgres refuses the
+/// savepoint, which is then not needed. Evaluates to the `QueryResult`.
+///
+/// Takes the same prefix and expects the same `RunQueryDsl` in scope as
+/// `__stream_chunks!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __get_result_in_savepoint {
+    ( $(.$await:tt)? ; $query:expr , $result:ty , $conn:expr , $savepoint:literal ) => {{
+        let savepoint = diesel::sql_query(concat!("SAVEPOINT ", $savepoint))
+            .execute($conn)
+            $(.$await)?
+            .is_ok();
+
+        let result = $query.get_result::<$result>($conn)$(.$await)?;
+
+        if savepoint {
+            if result.is_err() {
+                diesel::sql_query(concat!("ROLLBACK TO SAVEPOINT ", $savepoint))
+                    .execute($conn)
+                    $(.$await)?
+                    .unwrap();
+            }
+
+            diesel::sql_query(concat!("RELEASE SAVEPOINT ", $savepoint))
+                .execute($conn)
+                $(.$await)?
+                .unwrap();
+        }
+
+        result
+    }};
+}
+
+/// Resolves the cursor's bounds on behalf of `__stream_chunks!` and
+/// `dry_run!`, from `$options`' `from`/`to`, then its partitions, then table
+/// statistics when estimated, and then `MIN`/`MAX`. Evaluates to
+/// `(from, to)`, either of which is `None` when it could not be resolved,
+/// e.g. on an empty table.
+///
+/// Takes the same prefix and expects the same items in scope as
+/// `__stream_chunks!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __resolve_bounds {
+    ( $(.$await:tt)? ; $query:expr , $cursor_field:expr , $conn:expr , $options:ident , $instrument:ident ) => {{
+        use diesel::dsl::{max, min};
+        use diesel::QueryDsl;
+
+        let estimate_query = if $options.should_estimate_bounds() {
+            diesel_streamer::__estimate_bounds_query(&$cursor_field, backend_of(&*$conn))
+        } else {
+            None
+        };
+        let estimate = match estimate_query {
+            Some(estimate_query) => diesel_streamer::__get_result_in_savepoint!($(.$await)?;
+                estimate_query,
+                diesel_streamer::BoundsEstimate,
+                $conn,
+                "diesel_streamer_estimate"
+            )
+            .ok(),
+            None => None,
+        };
+        $instrument.record_estimate(estimate.as_ref());
+
+        let from = match $options
+            .from
+            .or_else(|| $options.partitions_start())
+            .or_else(|| estimate.and_then(|estimate| estimate.lower_bound()))
+        {
+            Some(from) => Some(from),
+            None if $options.should_resolve_exact_bounds() => $query
+                .select(min($cursor_field))
+                .get_result::<Option<_>>($conn)
+                $(.$await)?
+                .unwrap(),
+            None => None,
+        };
+
+        let to = match ($options.to, $options.partitions_end()) {
+            (Some(to), _) => Some(to),
+            // `None` when the partitions end at the type's minimum, and
+            // thus hold no cursor
+            (None, Some(partitions_end)) => diesel_streamer::SerialCursor::last_below(partitions_end),
+            (None, None) => match estimate.and_then(|estimate| estimate.upper_bound()) {
+                Some(to) => Some(to),
+                None if $options.should_resolve_exact_bounds() => $query
+                    .select(max($cursor_field))
+                    .get_result::<Option<_>>($conn)
+                    $(.$await)?
+                    .unwrap(),
+                None => None,
+            },
+        };
+
+        (from, to)
+    }};
+}
+
+/// Sleeps between two chunks, without blocking the runtime in async mode.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pause {
+    ( .await ; $pause:expr ) => {
+        diesel_streamer::__sleep($pause).await
+    };
+
+    ( ; $pause:expr ) => {
+        std::thread::sleep($pause)
+    };
+}
+
+#[cfg(feature = "async")]
+#[doc(hidden)]
+pub async fn __sleep(pause: std::time::Duration) {
+    tokio::time::sleep(pause).await;
+}
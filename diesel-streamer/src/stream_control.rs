@@ -0,0 +1,51 @@
+use std::convert::Infallible;
+use std::ops::ControlFlow;
+
+/// Output of a stream processor, deciding whether the stream goes on.
+///
+/// Processors may return (or resolve to) `()` to always continue,
+/// `ControlFlow<B>` to stop with `B` once they found what they were looking
+/// for, or `Result<(), E>` to stop with `E` on an error. Either way, the
+/// value is returned in [`StreamEnd::Stopped`](crate::StreamEnd::Stopped).
+pub trait StreamControl {
+    type Break;
+
+    fn into_control_flow(self) -> ControlFlow<Self::Break>;
+
+    /// Whether the processor stopped the stream because it failed, as told
+    /// apart in the metrics from stopping once done.
+    fn is_error(&self) -> bool {
+        false
+    }
+}
+
+impl StreamControl for () {
+    type Break = Infallible;
+
+    fn into_control_flow(self) -> ControlFlow<Infallible> {
+        ControlFlow::Continue(())
+    }
+}
+
+impl<B> StreamControl for ControlFlow<B> {
+    type Break = B;
+
+    fn into_control_flow(self) -> ControlFlow<B> {
+        self
+    }
+}
+
+impl<E> StreamControl for Result<(), E> {
+    type Break = E;
+
+    fn into_control_flow(self) -> ControlFlow<E> {
+        match self {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(error) => ControlFlow::Break(error),
+        }
+    }
+
+    fn is_error(&self) -> bool {
+        self.is_err()
+    }
+}
@@ -0,0 +1,248 @@
+/// Streams a Postgres table known by name at runtime only, without a
+/// `table!` schema or a `Queryable` struct, as [`DynamicRow`](crate::DynamicRow)s:
+/// maps of column names to JSON values.
+///
+/// Takes the table's name, e.g. `"users"` or `"public.users"`, a connection,
+/// optionally `cursor = "..."` naming an integer column to stream by, which
+/// defaults to the table's primary key, `options = ...` as taken by
+/// `stream_serial_table!` for an `i64` cursor, and the processor.
+///
+/// Evaluates to a `Result` holding the [`StreamEnd`](crate::StreamEnd), or
+/// a [`DynamicTableError`](crate::DynamicTableError) when the table's
+/// columns cannot be read, or when it does not exist or has no cursor to
+/// stream by. A `from` above or a `to` below every value of the cursor's
+/// type streams nothing.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_dynamic_table, DynamicRow};
+///
+/// async fn main() {
+///     let mut conn = pool.get().await.unwrap();
+///     let table_name = std::env::args().nth(1).unwrap();
+///
+///     stream_dynamic_table!(table_name, conn, |rows: Vec<DynamicRow>| async move {
+///         for row in rows {
+///             println!("{}", row["id"]);
+///         }
+///     })?;
+/// }
+/// ```
+#[cfg(all(feature = "async", feature = "dynamic"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_stream_dynamic_table {
+    (@stream $table:expr , $conn:expr , [$($cursor:expr)?] , $options:expr , $processor:expr) => {{
+        use diesel_async::RunQueryDsl;
+
+        let table_name = $table;
+        let table_name: &str = table_name.as_ref();
+        let columns = diesel_streamer::DynamicTable::columns_query(table_name)
+            .load::<diesel_streamer::DynamicColumn>($conn)
+            .await;
+        let table = match columns {
+            Ok(columns) => diesel_streamer::DynamicTable::new(
+                table_name,
+                columns,
+                diesel_streamer::__first!($(Some($cursor),)? None),
+            ),
+            Err(error) => Err(diesel_streamer::DynamicTableError::Query(error)),
+        };
+
+        match table {
+            Ok(table) => {
+                let options = $options;
+
+                Ok(match table.cursor_type() {
+                    diesel_streamer::CursorType::SmallInt => {
+                        match diesel_streamer::__narrow_options::<i16>(options, 500) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__async_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::SmallInt>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                    diesel_streamer::CursorType::Integer => {
+                        match diesel_streamer::__narrow_options::<i32>(options, 500) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__async_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::Integer>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                    diesel_streamer::CursorType::BigInt => {
+                        match diesel_streamer::__narrow_options::<i64>(options, 500) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__async_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::BigInt>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }};
+
+    ( $table:expr , $conn:expr , cursor = $cursor:expr , options = $options:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__async_stream_dynamic_table!(@stream $table, $conn, [$cursor], $options, $processor)
+    };
+
+    ( $table:expr , $conn:expr , cursor = $cursor:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__async_stream_dynamic_table!(@stream $table, $conn, [$cursor], diesel_streamer::StreamOptions::new(), $processor)
+    };
+
+    ( $table:expr , $conn:expr , options = $options:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__async_stream_dynamic_table!(@stream $table, $conn, [], $options, $processor)
+    };
+
+    ( $table:expr , $conn:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__async_stream_dynamic_table!(@stream $table, $conn, [], diesel_streamer::StreamOptions::new(), $processor)
+    };
+}
+
+/// Streams a Postgres table known by name at runtime only, without a
+/// `table!` schema or a `Queryable` struct, as [`DynamicRow`](crate::DynamicRow)s:
+/// maps of column names to JSON values.
+///
+/// Takes the table's name, e.g. `"users"` or `"public.users"`, a connection,
+/// optionally `cursor = "..."` naming an integer column to stream by, which
+/// defaults to the table's primary key, `options = ...` as taken by
+/// `stream_serial_table!` for an `i64` cursor, and the processor.
+///
+/// Evaluates to a `Result` holding the [`StreamEnd`](crate::StreamEnd), or
+/// a [`DynamicTableError`](crate::DynamicTableError) when the table's
+/// columns cannot be read, or when it does not exist or has no cursor to
+/// stream by. A `from` above or a `to` below every value of the cursor's
+/// type streams nothing.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_dynamic_table, DynamicRow};
+///
+/// fn main() {
+///     let mut conn = pool.get().unwrap();
+///     let table_name = std::env::args().nth(1).unwrap();
+///
+///     stream_dynamic_table!(table_name, conn, |rows: Vec<DynamicRow>| {
+///         for row in rows {
+///             println!("{}", row["id"]);
+///         }
+///     })?;
+/// }
+/// ```
+#[cfg(all(feature = "sync", feature = "dynamic"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_stream_dynamic_table {
+    (@stream $table:expr , $conn:expr , [$($cursor:expr)?] , $options:expr , $processor:expr) => {{
+        use diesel::RunQueryDsl;
+
+        let table_name = $table;
+        let table_name: &str = table_name.as_ref();
+        let columns = diesel_streamer::DynamicTable::columns_query(table_name)
+            .load::<diesel_streamer::DynamicColumn>($conn);
+        let table = match columns {
+            Ok(columns) => diesel_streamer::DynamicTable::new(
+                table_name,
+                columns,
+                diesel_streamer::__first!($(Some($cursor),)? None),
+            ),
+            Err(error) => Err(diesel_streamer::DynamicTableError::Query(error)),
+        };
+
+        match table {
+            Ok(table) => {
+                let options = $options;
+
+                Ok(match table.cursor_type() {
+                    diesel_streamer::CursorType::SmallInt => {
+                        match diesel_streamer::__narrow_options::<i16>(options, 100000) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__sync_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::SmallInt>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                    diesel_streamer::CursorType::Integer => {
+                        match diesel_streamer::__narrow_options::<i32>(options, 100000) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__sync_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::Integer>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                    diesel_streamer::CursorType::BigInt => {
+                        match diesel_streamer::__narrow_options::<i64>(options, 100000) {
+                            Some(options) => diesel_streamer::__widen_stream_end(
+                                diesel_streamer::__sync_stream_serial_table!(
+                                    table.source(),
+                                    table.cursor_column::<diesel::sql_types::BigInt>(),
+                                    $conn,
+                                    select = table.select_clause(),
+                                    options = options,
+                                    $processor
+                                )
+                            ),
+                            None => diesel_streamer::StreamEnd::Completed { cursor: None },
+                        }
+                    }
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }};
+
+    ( $table:expr , $conn:expr , cursor = $cursor:expr , options = $options:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__sync_stream_dynamic_table!(@stream $table, $conn, [$cursor], $options, $processor)
+    };
+
+    ( $table:expr , $conn:expr , cursor = $cursor:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__sync_stream_dynamic_table!(@stream $table, $conn, [$cursor], diesel_streamer::StreamOptions::new(), $processor)
+    };
+
+    ( $table:expr , $conn:expr , options = $options:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__sync_stream_dynamic_table!(@stream $table, $conn, [], $options, $processor)
+    };
+
+    ( $table:expr , $conn:expr , $processor:expr $(,)? ) => {
+        diesel_streamer::__sync_stream_dynamic_table!(@stream $table, $conn, [], diesel_streamer::StreamOptions::new(), $processor)
+    };
+}
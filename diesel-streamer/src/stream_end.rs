@@ -0,0 +1,39 @@
+use std::convert::Infallible;
+
+/// How a call to `stream_serial_table!` ended.
+///
+/// `cursor` is where a follow-up stream should start from: every row below it
+/// has been processed. It is `None` when there was nothing to stream, or
+/// once the row at the cursor type's maximum is processed, as no cursor is
+/// left to resume from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEnd<T, B = Infallible> {
+    /// Every chunk up to the cursor's end was processed.
+    Completed { cursor: Option<T> },
+    /// The stream was cancelled between two chunks.
+    Cancelled { cursor: Option<T> },
+    /// The processor stopped the stream by returning `ControlFlow::Break` or
+    /// `Err`. `cursor` is the beginning of the chunk it stopped on.
+    Stopped { cursor: Option<T>, reason: B },
+}
+
+impl<T: Copy, B> StreamEnd<T, B> {
+    #[must_use]
+    pub fn cursor(&self) -> Option<T> {
+        match *self {
+            StreamEnd::Completed { cursor }
+            | StreamEnd::Cancelled { cursor }
+            | StreamEnd::Stopped { cursor, .. } => cursor,
+        }
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, StreamEnd::Cancelled { .. })
+    }
+
+    #[must_use]
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, StreamEnd::Stopped { .. })
+    }
+}
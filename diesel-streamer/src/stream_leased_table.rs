@@ -0,0 +1,395 @@
+/// Streams a serial table cooperatively with other workers, one leased unit
+/// at a time, as coordinated by a [`LeaseCoordinator`](crate::LeaseCoordinator).
+///
+/// Takes the same query, serial field, connection and processor as
+/// `stream_serial_table!`, along with `coordinator = ...` and optionally
+/// either `chunk_size = ...` (defaulting to 500) or `options = ...`. Of the
+/// [`StreamOptions`](crate::StreamOptions), the chunk size, name, pause,
+/// cancellation and progress apply, while the cursor's bounds come from the
+/// leased units and the shard from the coordinator. Units must have been
+/// planned beforehand with [`plan_query`](crate::LeaseCoordinator::plan_query).
+/// The processor is evaluated for every chunk, so pass the closure itself
+/// rather than a variable holding it.
+///
+/// Each claimed unit is streamed chunk by chunk, renewing its lease before
+/// every chunk, and marked as done once streamed. The lease must thus
+/// outlast the processing of a chunk: a unit whose lease was lost to another
+/// worker, e.g. after a chunk outlived the lease, is left to that worker.
+/// When every remaining unit is leased by other workers, the worker waits
+/// `poll_interval` and claims again, so that it takes over the units of
+/// workers that died. With a [`shard`](crate::LeaseCoordinator::shard), only
+/// the shard's rows of each unit are streamed. Progress is reported through
+/// each unit, from its start to its end.
+///
+/// Evaluates to a `Result` holding a [`StreamEnd`](crate::StreamEnd):
+/// `Completed` once every unit is done, with the cursor past the last unit
+/// this worker streamed, or `Stopped` or `Cancelled` when the processor
+/// stopped the stream or it was cancelled, leaving the lease on the current
+/// unit to expire. A failing lease query, e.g. a claim or a heartbeat, ends
+/// the stream with its error, likewise leaving the lease to expire.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_leased_table, LeaseCoordinator, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::events::dsl::{events, id};
+///
+///     let mut conn = pool.get().await.unwrap();
+///     let coordinator = LeaseCoordinator::new("backfill_events", hostname);
+///
+///     stream_leased_table!(events, id, conn, coordinator = coordinator, chunk_size = 200, |events: Vec<Event>| async {
+///         // do work here
+///     })
+///     .unwrap();
+///
+///     // named, and reporting its progress through each unit
+///     let options = StreamOptions::new()
+///         .chunk_size(200)
+///         .name("backfill_events")
+///         .on_progress(|progress| println!("{:.1}%", progress.percent_complete()));
+///
+///     stream_leased_table!(events, id, conn, coordinator = coordinator, options = options, |events: Vec<Event>| async {
+///         // do work here
+///     })
+///     .unwrap();
+/// }
+/// ```
+#[cfg(all(feature = "async", feature = "postgres"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_stream_leased_table {
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , options = $options:expr , $processor:expr ) => {{
+        use diesel::prelude::*;
+        use diesel_async::RunQueryDsl;
+
+        fn backend_of<C: diesel_async::AsyncConnection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let coordinator: diesel_streamer::LeaseCoordinator = $coordinator;
+        let mut options: diesel_streamer::StreamOptions<_> = $options;
+        options.shard = coordinator.shard.clone();
+        let chunk_size = options.chunk_size.unwrap_or(500);
+
+        let instrument =
+            diesel_streamer::instrument::StreamInstrument::start(options.name.as_deref());
+        let mut cursor = None;
+
+        'leases: loop {
+            let lease = coordinator
+                .claim_query()
+                .get_result::<diesel_streamer::LeaseRow>($conn)
+                .await
+                .optional();
+
+            let lease = match lease {
+                Ok(Some(lease)) => match lease.lease() {
+                    Some(lease) => lease,
+                    None => {
+                        break Err(diesel::result::Error::DeserializationError(
+                            "the leased unit is empty or does not fit in the cursor's type".into(),
+                        ))
+                    }
+                },
+                Ok(None) => {
+                    let remaining = coordinator
+                        .remaining_query()
+                        .get_result::<diesel_streamer::RemainingUnits>($conn)
+                        .await;
+
+                    match remaining {
+                        Ok(remaining) if remaining.remaining == 0 => {
+                            break Ok(diesel_streamer::StreamEnd::Completed { cursor });
+                        }
+                        Ok(_) => {}
+                        Err(error) => break Err(error),
+                    }
+
+                    diesel_streamer::__pause!(.await; coordinator.poll_interval);
+                    continue;
+                }
+                Err(error) => break Err(error),
+            };
+            let diesel_streamer::Lease { mut from, to, .. } = lease;
+            let mut progress = diesel_streamer::Progress::start(from, to);
+
+            loop {
+                if let Some(pause) = options.pause.filter(|_| progress.chunks > 0) {
+                    diesel_streamer::__pause!(.await; pause);
+                }
+
+                if options.is_cancelled() {
+                    instrument.record_cancellation(from);
+
+                    break 'leases Ok(diesel_streamer::StreamEnd::Cancelled { cursor: Some(from) });
+                }
+
+                match coordinator.heartbeat_query(&lease).execute($conn).await {
+                    Ok(0) => continue 'leases,
+                    Ok(_) => {}
+                    Err(error) => break 'leases Err(error),
+                }
+
+                let chunk_to = std::cmp::min(diesel_streamer::SerialCursor::chunk_end(from, chunk_size), to);
+                let mut chunk = instrument.chunk(from, chunk_to);
+
+                let control_flow = diesel_streamer::__async_stream_serial_table!(
+                    @chunk $query, $cursor_field, $conn, [], options, from, chunk_to, chunk, $processor
+                );
+
+                match control_flow {
+                    std::ops::ControlFlow::Continue(rows) => {
+                        let next = diesel_streamer::SerialCursor::checked_next(chunk_to);
+                        from = next.unwrap_or(chunk_to);
+
+                        progress.record_chunk(from, rows);
+                        options.report_progress(&progress);
+
+                        // Nothing follows the cursor type's maximum
+                        if next.is_none() || from > to {
+                            break;
+                        }
+                    }
+                    std::ops::ControlFlow::Break(reason) => {
+                        instrument.record_stop(from);
+
+                        break 'leases Ok(diesel_streamer::StreamEnd::Stopped {
+                            cursor: Some(from),
+                            reason,
+                        });
+                    }
+                }
+            }
+
+            if let Err(error) = coordinator.complete_query(&lease).execute($conn).await {
+                break Err(error);
+            }
+            cursor = Some(from);
+        }
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , chunk_size = $chunk_size:expr , $processor:expr ) => {{
+        use diesel_streamer::__async_stream_leased_table as stream_leased_table;
+
+        stream_leased_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            coordinator = $coordinator,
+            options = diesel_streamer::StreamOptions::new().chunk_size($chunk_size),
+            $processor
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , $processor:expr ) => {{
+        use diesel_streamer::__async_stream_leased_table as stream_leased_table;
+
+        stream_leased_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            coordinator = $coordinator,
+            options = diesel_streamer::StreamOptions::new(),
+            $processor
+        )
+    }};
+}
+
+/// Streams a serial table cooperatively with other workers, one leased unit
+/// at a time, as coordinated by a [`LeaseCoordinator`](crate::LeaseCoordinator).
+///
+/// Takes the same query, serial field, connection and processor as
+/// `stream_serial_table!`, along with `coordinator = ...` and optionally
+/// either `chunk_size = ...` (defaulting to 500) or `options = ...`. Of the
+/// [`StreamOptions`](crate::StreamOptions), the chunk size, name, pause,
+/// cancellation and progress apply, while the cursor's bounds come from the
+/// leased units and the shard from the coordinator. Units must have been
+/// planned beforehand with [`plan_query`](crate::LeaseCoordinator::plan_query).
+/// The processor is evaluated for every chunk, so pass the closure itself
+/// rather than a variable holding it.
+///
+/// Each claimed unit is streamed chunk by chunk, renewing its lease before
+/// every chunk, and marked as done once streamed. The lease must thus
+/// outlast the processing of a chunk: a unit whose lease was lost to another
+/// worker, e.g. after a chunk outlived the lease, is left to that worker.
+/// When every remaining unit is leased by other workers, the worker waits
+/// `poll_interval` and claims again, so that it takes over the units of
+/// workers that died. With a [`shard`](crate::LeaseCoordinator::shard), only
+/// the shard's rows of each unit are streamed. Progress is reported through
+/// each unit, from its start to its end.
+///
+/// Evaluates to a `Result` holding a [`StreamEnd`](crate::StreamEnd):
+/// `Completed` once every unit is done, with the cursor past the last unit
+/// this worker streamed, or `Stopped` or `Cancelled` when the processor
+/// stopped the stream or it was cancelled, leaving the lease on the current
+/// unit to expire. A failing lease query, e.g. a claim or a heartbeat, ends
+/// the stream with its error, likewise leaving the lease to expire.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{stream_leased_table, LeaseCoordinator, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::events::dsl::{events, id};
+///
+///     let mut conn = pool.get().unwrap();
+///     let coordinator = LeaseCoordinator::new("backfill_events", hostname);
+///
+///     stream_leased_table!(events, id, conn, coordinator = coordinator, chunk_size = 200, |events: Vec<Event>| {
+///         // do work here
+///     })
+///     .unwrap();
+///
+///     // named, and reporting its progress through each unit
+///     let options = StreamOptions::new()
+///         .chunk_size(200)
+///         .name("backfill_events")
+///         .on_progress(|progress| println!("{:.1}%", progress.percent_complete()));
+///
+///     stream_leased_table!(events, id, conn, coordinator = coordinator, options = options, |events: Vec<Event>| {
+///         // do work here
+///     })
+///     .unwrap();
+/// }
+/// ```
+#[cfg(all(feature = "sync", feature = "postgres"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_stream_leased_table {
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , options = $options:expr , $processor:expr ) => {{
+        use diesel::prelude::*;
+        use diesel::RunQueryDsl;
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let coordinator: diesel_streamer::LeaseCoordinator = $coordinator;
+        let mut options: diesel_streamer::StreamOptions<_> = $options;
+        options.shard = coordinator.shard.clone();
+        let chunk_size = options.chunk_size.unwrap_or(500);
+
+        let instrument =
+            diesel_streamer::instrument::StreamInstrument::start(options.name.as_deref());
+        let mut cursor = None;
+
+        'leases: loop {
+            let lease = coordinator
+                .claim_query()
+                .get_result::<diesel_streamer::LeaseRow>($conn)
+                .optional();
+
+            let lease = match lease {
+                Ok(Some(lease)) => match lease.lease() {
+                    Some(lease) => lease,
+                    None => {
+                        break Err(diesel::result::Error::DeserializationError(
+                            "the leased unit is empty or does not fit in the cursor's type".into(),
+                        ))
+                    }
+                },
+                Ok(None) => {
+                    let remaining = coordinator
+                        .remaining_query()
+                        .get_result::<diesel_streamer::RemainingUnits>($conn);
+
+                    match remaining {
+                        Ok(remaining) if remaining.remaining == 0 => {
+                            break Ok(diesel_streamer::StreamEnd::Completed { cursor });
+                        }
+                        Ok(_) => {}
+                        Err(error) => break Err(error),
+                    }
+
+                    diesel_streamer::__pause!(; coordinator.poll_interval);
+                    continue;
+                }
+                Err(error) => break Err(error),
+            };
+            let diesel_streamer::Lease { mut from, to, .. } = lease;
+            let mut progress = diesel_streamer::Progress::start(from, to);
+
+            loop {
+                if let Some(pause) = options.pause.filter(|_| progress.chunks > 0) {
+                    diesel_streamer::__pause!(; pause);
+                }
+
+                if options.is_cancelled() {
+                    instrument.record_cancellation(from);
+
+                    break 'leases Ok(diesel_streamer::StreamEnd::Cancelled { cursor: Some(from) });
+                }
+
+                match coordinator.heartbeat_query(&lease).execute($conn) {
+                    Ok(0) => continue 'leases,
+                    Ok(_) => {}
+                    Err(error) => break 'leases Err(error),
+                }
+
+                let chunk_to = std::cmp::min(diesel_streamer::SerialCursor::chunk_end(from, chunk_size), to);
+                let mut chunk = instrument.chunk(from, chunk_to);
+
+                let control_flow = diesel_streamer::__sync_stream_serial_table!(
+                    @chunk $query, $cursor_field, $conn, [], options, from, chunk_to, chunk, $processor
+                );
+
+                match control_flow {
+                    std::ops::ControlFlow::Continue(rows) => {
+                        let next = diesel_streamer::SerialCursor::checked_next(chunk_to);
+                        from = next.unwrap_or(chunk_to);
+
+                        progress.record_chunk(from, rows);
+                        options.report_progress(&progress);
+
+                        // Nothing follows the cursor type's maximum
+                        if next.is_none() || from > to {
+                            break;
+                        }
+                    }
+                    std::ops::ControlFlow::Break(reason) => {
+                        instrument.record_stop(from);
+
+                        break 'leases Ok(diesel_streamer::StreamEnd::Stopped {
+                            cursor: Some(from),
+                            reason,
+                        });
+                    }
+                }
+            }
+
+            if let Err(error) = coordinator.complete_query(&lease).execute($conn) {
+                break Err(error);
+            }
+            cursor = Some(from);
+        }
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , chunk_size = $chunk_size:expr , $processor:expr ) => {{
+        use diesel_streamer::__sync_stream_leased_table as stream_leased_table;
+
+        stream_leased_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            coordinator = $coordinator,
+            options = diesel_streamer::StreamOptions::new().chunk_size($chunk_size),
+            $processor
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , coordinator = $coordinator:expr , $processor:expr ) => {{
+        use diesel_streamer::__sync_stream_leased_table as stream_leased_table;
+
+        stream_leased_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            coordinator = $coordinator,
+            options = diesel_streamer::StreamOptions::new(),
+            $processor
+        )
+    }};
+}
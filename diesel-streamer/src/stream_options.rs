@@ -0,0 +1,354 @@
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::{Bounds, Cancellation, Progress, Shard};
+
+/// Configures how `stream_serial_table!` walks through a serial table.
+///
+/// Every option is optional. Whatever is left unset falls back to the
+/// defaults of the macro being used, and `from`/`to` fall back to the
+/// minimum and maximum values of the cursor field.
+///
+/// # Examples
+///
+/// ```
+/// use diesel_streamer::StreamOptions;
+///
+/// let options = StreamOptions::new()
+///     .chunk_size(200)
+///     .from(5)
+///     .moving_upper_bound();
+///
+/// assert_eq!(options.chunk_size, Some(200));
+/// assert_eq!(options.from, Some(5));
+/// assert_eq!(options.to, None);
+/// ```
+#[non_exhaustive]
+pub struct StreamOptions<T> {
+    pub chunk_size: Option<T>,
+    pub from: Option<T>,
+    pub to: Option<T>,
+    pub upper_bound: UpperBound,
+    pub bounds: Bounds,
+    pub partitions: Option<Vec<Range<T>>>,
+    pub shard: Option<Shard>,
+    pub name: Option<String>,
+    pub transaction_per_chunk: bool,
+    pub lock_timeout: Option<Duration>,
+    pub retries: usize,
+    pub pause: Option<Duration>,
+    pub cancellation: Option<Box<dyn Cancellation + Send + Sync>>,
+    pub progress: Option<OnProgress<T>>,
+}
+
+/// A callback receiving a [`Progress`] snapshot after each chunk.
+pub type OnProgress<T> = Box<dyn FnMut(&Progress<T>) + Send>;
+
+/// Decides what happens once the stream reaches its upper bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpperBound {
+    /// The upper bound is read once, before the first chunk.
+    Fixed,
+    /// The upper bound is read again whenever the stream reaches it, so rows
+    /// inserted while streaming are also processed. Streaming stops when a
+    /// re-read returns the same maximum, or after `max_refreshes` re-reads.
+    Moving { max_refreshes: Option<usize> },
+}
+
+impl<T> StreamOptions<T> {
+    #[must_use]
+    pub fn new() -> Self {
+        StreamOptions {
+            chunk_size: None,
+            from: None,
+            to: None,
+            upper_bound: UpperBound::Fixed,
+            bounds: Bounds::Exact,
+            partitions: None,
+            shard: None,
+            name: None,
+            transaction_per_chunk: false,
+            lock_timeout: None,
+            retries: 0,
+            pause: None,
+            cancellation: None,
+            progress: None,
+        }
+    }
+
+    #[must_use]
+    pub fn chunk_size(mut self, chunk_size: T) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Sets the cursor's beginning. Accepts both `5` and `Some(5)`.
+    #[must_use]
+    pub fn from(mut self, from: impl Into<Option<T>>) -> Self {
+        self.from = from.into();
+        self
+    }
+
+    /// Sets the cursor's end. Accepts both `50` and `Some(50)`.
+    ///
+    /// An explicit end is never refreshed, even with a moving upper bound.
+    #[must_use]
+    pub fn to(mut self, to: impl Into<Option<T>>) -> Self {
+        self.to = to.into();
+        self
+    }
+
+    /// Keeps re-reading the upper bound until it stops moving.
+    #[must_use]
+    pub fn moving_upper_bound(mut self) -> Self {
+        self.upper_bound = UpperBound::Moving {
+            max_refreshes: None,
+        };
+        self
+    }
+
+    /// Keeps re-reading the upper bound until it stops moving, at most
+    /// `max_refreshes` times.
+    #[must_use]
+    pub fn max_upper_bound_refreshes(mut self, max_refreshes: usize) -> Self {
+        self.upper_bound = UpperBound::Moving {
+            max_refreshes: Some(max_refreshes),
+        };
+        self
+    }
+
+    /// Estimates the cursor's beginning and end from table statistics instead
+    /// of scanning the table with `MIN`/`MAX`, which is slow on unindexed
+    /// cursor fields or selective filters. Bounds that cannot be estimated
+    /// still go through `MIN`/`MAX`. See [`Bounds::Estimated`].
+    #[must_use]
+    pub fn estimated_bounds(mut self) -> Self {
+        self.bounds = Bounds::Estimated {
+            fallback_to_exact: true,
+        };
+        self
+    }
+
+    /// Estimates the cursor's beginning and end from table statistics, and
+    /// streams nothing when they cannot be estimated.
+    #[must_use]
+    pub fn estimated_bounds_without_fallback(mut self) -> Self {
+        self.bounds = Bounds::Estimated {
+            fallback_to_exact: false,
+        };
+        self
+    }
+
+    /// Streams one chunk per range of `partitions`, e.g. as planned by
+    /// `plan_partitions!` so chunks hold about as many rows as each other,
+    /// instead of chunks of `chunk_size` cursor values.
+    ///
+    /// Unless set, `from` and `to` default to the start of the first range
+    /// and the end of the last one. Ranges exclude their end, so none covers
+    /// the cursor type's maximum: set `to` to it for its row to be streamed,
+    /// in a last chunk of `chunk_size` values. Past the last range, e.g. with
+    /// a moving upper bound, chunks are `chunk_size` wide again.
+    #[must_use]
+    pub fn partitions(mut self, partitions: Vec<Range<T>>) -> Self {
+        self.partitions = Some(partitions);
+        self
+    }
+
+    /// Only streams the rows of `shard`, e.g. as read with
+    /// [`Shard::from_env`], combining its predicate with each chunk's range.
+    #[must_use]
+    pub fn shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Names the stream in tracing spans and in the `stream` label of its
+    /// metrics.
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Runs the statement of each chunk in its own transaction. Only used by
+    /// the batched writes, such as `update_in_batches!`.
+    #[must_use]
+    pub fn transaction_per_chunk(mut self) -> Self {
+        self.transaction_per_chunk = true;
+        self
+    }
+
+    /// Gives up on a chunk when its locks cannot be acquired within
+    /// `lock_timeout`, through Postgres' `SET LOCAL lock_timeout`, ending the
+    /// batched write with a [`BatchError`](crate::BatchError) holding the
+    /// chunk. Implies [`transaction_per_chunk`](Self::transaction_per_chunk).
+    #[must_use]
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.transaction_per_chunk = true;
+        self.lock_timeout = Some(lock_timeout);
+        self
+    }
+
+    /// The statement setting the lock timeout at the start of a chunk's
+    /// transaction, if any.
+    #[must_use]
+    pub fn lock_timeout_statement(&self) -> Option<String> {
+        self.lock_timeout.map(|lock_timeout| {
+            format!("SET LOCAL lock_timeout = '{}ms'", lock_timeout.as_millis())
+        })
+    }
+
+    /// Tries a failed chunk of a batched write again, up to `retries` times,
+    /// e.g. after a lock timeout, before giving up on it.
+    #[must_use]
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sleeps for `pause` between two chunks, e.g. to leave replicas and
+    /// vacuum some room while purging a big table.
+    #[must_use]
+    pub fn pause(mut self, pause: Duration) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Stops the stream between chunks once `cancellation` is cancelled.
+    #[must_use]
+    pub fn cancellation(mut self, cancellation: impl Cancellation + Send + Sync + 'static) -> Self {
+        self.cancellation = Some(Box::new(cancellation));
+        self
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|cancellation| cancellation.is_cancelled())
+    }
+
+    /// Calls `on_progress` after each chunk has been processed.
+    #[must_use]
+    pub fn on_progress(mut self, on_progress: impl FnMut(&Progress<T>) + Send + 'static) -> Self {
+        self.progress = Some(Box::new(on_progress));
+        self
+    }
+
+    pub fn report_progress(&mut self, progress: &Progress<T>) {
+        if let Some(on_progress) = self.progress.as_mut() {
+            on_progress(progress);
+        }
+    }
+
+    /// Whether a bound is left for table statistics to estimate.
+    #[must_use]
+    pub fn should_estimate_bounds(&self) -> bool {
+        matches!(self.bounds, Bounds::Estimated { .. })
+            && (self.from.is_none() || self.to.is_none())
+            && self.partitions.as_ref().is_none_or(Vec::is_empty)
+    }
+
+    /// Whether a bound that is neither set nor estimated goes through
+    /// `MIN`/`MAX`.
+    #[must_use]
+    pub fn should_resolve_exact_bounds(&self) -> bool {
+        match self.bounds {
+            Bounds::Exact => true,
+            Bounds::Estimated { fallback_to_exact } => fallback_to_exact,
+        }
+    }
+
+    /// Whether the upper bound should be read again after `refreshes`
+    /// previous re-reads.
+    #[must_use]
+    pub fn should_refresh_upper_bound(&self, refreshes: usize) -> bool {
+        if self.to.is_some() {
+            return false;
+        }
+
+        match self.upper_bound {
+            UpperBound::Fixed => false,
+            UpperBound::Moving { max_refreshes } => {
+                max_refreshes.is_none_or(|max_refreshes| refreshes < max_refreshes)
+            }
+        }
+    }
+}
+
+impl<T: Copy + PartialOrd> StreamOptions<T> {
+    /// The start of the first partition, if any.
+    #[must_use]
+    pub fn partitions_start(&self) -> Option<T> {
+        self.partitions.as_ref()?.first().map(|range| range.start)
+    }
+
+    /// The end of the last partition, exclusive, if any.
+    #[must_use]
+    pub fn partitions_end(&self) -> Option<T> {
+        self.partitions.as_ref()?.last().map(|range| range.end)
+    }
+
+    /// The exclusive end of the partition chunk starting at `from`: the end
+    /// of the range holding `from`, or the start of the next range when
+    /// `from` falls before it. `None` past the last range.
+    #[must_use]
+    pub fn partition_end(&self, from: T) -> Option<T> {
+        self.partitions
+            .as_ref()?
+            .iter()
+            .find(|range| from < range.end)
+            .map(|range| {
+                if from < range.start {
+                    range.start
+                } else {
+                    range.end
+                }
+            })
+    }
+}
+
+#[cfg(feature = "indicatif")]
+impl<T: crate::SerialCursor + 'static> StreamOptions<T> {
+    /// Reports progress on `progress_bar`, measured in cursor values.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn progress_bar(self, progress_bar: indicatif::ProgressBar) -> Self {
+        self.on_progress(move |progress| {
+            let length = progress.to.as_f64() + 1.0 - progress.from.as_f64();
+            let position = progress.cursor.as_f64() - progress.from.as_f64();
+
+            progress_bar.set_length(length.max(0.0) as u64);
+            progress_bar.set_position(position.max(0.0) as u64);
+            progress_bar.set_message(format!("{} rows", progress.rows));
+        })
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StreamOptions<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamOptions")
+            .field("chunk_size", &self.chunk_size)
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("upper_bound", &self.upper_bound)
+            .field("bounds", &self.bounds)
+            .field("partitions", &self.partitions)
+            .field("shard", &self.shard)
+            .field("name", &self.name)
+            .field("transaction_per_chunk", &self.transaction_per_chunk)
+            .field("lock_timeout", &self.lock_timeout)
+            .field("retries", &self.retries)
+            .field("pause", &self.pause)
+            .field("cancellation", &self.cancellation.is_some())
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl<T> Default for StreamOptions<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
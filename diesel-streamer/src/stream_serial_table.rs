@@ -6,7 +6,28 @@
 ///
 /// - Chunk size is 500
 /// - Cursor's beginning is the minimum value of the serial table
-/// - Cursor's end is the maximum value of the serial table
+/// - Cursor's end is the maximum value of the serial table, read once
+///   before the first chunk (see [`StreamOptions::moving_upper_bound`])
+///
+/// Evaluates to a [`StreamEnd`] holding the cursor a follow-up stream
+/// should start from.
+///
+/// The processor may return `()`, `ControlFlow<B>` or `Result<(), E>` (see
+/// [`StreamControl`]). `ControlFlow::Break` and `Err` stop the stream before
+/// the next chunk is loaded and end up in [`StreamEnd::Stopped`].
+///
+/// `$query` may have a select clause of its own, which chunks are loaded
+/// with. Pass `select = ...` to load other columns instead, e.g. a tuple of
+/// columns or `Row::as_select()`: it replaces `$query`'s select clause, if
+/// any. The bounds are read with `$query`'s select clause replaced by
+/// `MIN`/`MAX` of the cursor field.
+///
+/// `$query` may be a table, a filtered query, an inner or left join, or a
+/// boxed query. The cursor field must belong to the driving table of the
+/// query, i.e. the left-hand side of its joins. `$query` is evaluated again
+/// for every statement: pass boxed queries as the expression (or function
+/// call) that builds them, and `query.clone()` for a query held in a
+/// variable.
 ///
 ///
 /// # Examples
@@ -14,7 +35,7 @@
 /// Stream a serial table using different configurations.
 ///
 /// ```ignore
-/// use diesel_streamer::stream_serial_table;
+/// use diesel_streamer::{stream_serial_table, StreamOptions};
 ///
 /// async fn main() {
 ///     use crate::schema::some_table::dsl::{some_table, serial_field};
@@ -43,14 +64,146 @@
 ///    stream_serial_table!(some_table, serial_field, conn,  chunk_size, beginning_id, end_id, |streamed_table_data| async {
 ///         // do work here
 ///     });
+///
+///   // with options, e.g. to also process rows inserted while streaming
+///    let options = StreamOptions::new().chunk_size(chunk_size).moving_upper_bound();
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| async {
+///         // do work here
+///     });
+///
+///   // with a cancellation handle checked between chunks
+///    let cancellation = tokio_util::sync::CancellationToken::new();
+///    let options = StreamOptions::new().cancellation(cancellation.clone());
+///    let stream_end = stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| async {
+///         // do work here
+///     });
+///
+///    // resume later on from where the stream stopped
+///    let beginning_id = stream_end.cursor();
+///
+///   // with progress reported after each chunk
+///    let options = StreamOptions::new().on_progress(|progress| {
+///         println!("{:.1}% done, {} rows, eta {:?}", progress.percent_complete(), progress.rows, progress.eta());
+///     });
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| async {
+///         // do work here
+///     });
+///
+///   // with a projection, loading only the selected columns
+///    stream_serial_table!(some_table, serial_field, conn, select = (serial_field, name), |streamed_table_data: Vec<(i32, String)>| async {
+///         // do work here
+///     });
+///
+///   // with a join, by a cursor field of the driving table
+///    stream_serial_table!(some_table.inner_join(other_table), serial_field, conn, |streamed_table_data: Vec<(SomeTable, OtherTable)>| async {
+///         // do work here
+///     });
+///
+///   // with only the rows of this worker's shard, e.g. worker 2 of 4
+///    let options = StreamOptions::new().shard(Shard::from_env().unwrap());
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| async {
+///         // do work here
+///     });
 /// }
 /// ```
 #[allow(clippy::module_name_repetitions)]
 #[cfg(feature = "async")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! stream_serial_table {
+macro_rules! __async_stream_serial_table {
+    (@stream $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:expr , $stream_processor:expr) => {{
+        use diesel::prelude::*;
+        use diesel_async::RunQueryDsl;
+
+        fn backend_of<C: diesel_async::AsyncConnection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+
+        diesel_streamer::__stream_chunks!(.await;
+            $query,
+            $cursor_field,
+            $conn,
+            options,
+            500,
+            |from, chunk_to, chunk| {
+                diesel_streamer::__async_stream_serial_table!(
+                    @chunk $query, $cursor_field, $conn, [$($select)?], options, from, chunk_to, chunk, $stream_processor
+                )
+            }
+        )
+    }};
+
+    // Loads and processes the chunk `$from..=$chunk_to` on behalf of `@stream`
+    // and `stream_leased_table!`, with the same items in scope as `@stream`.
+    // Evaluates to a `ControlFlow` like the body of `__stream_chunks!`.
+    (@chunk $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:ident , $from:ident , $chunk_to:ident , $chunk:ident , $stream_processor:expr) => {{
+        let shard_filter = $options.shard.as_ref().map(|shard| {
+            diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+        });
+
+        let streamed_data = match shard_filter {
+            Some(shard_filter) => {
+                let chunk_query = $query
+                    .filter($cursor_field.eq_any($from..=$chunk_to))
+                    .filter(shard_filter)
+                    $(.select($select))?;
+                $chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                chunk_query.load($conn).await
+            }
+            None => {
+                let chunk_query = $query
+                    .filter($cursor_field.eq_any($from..=$chunk_to))
+                    $(.select($select))?;
+                $chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                chunk_query.load($conn).await
+            }
+        };
+        $chunk.record_query(&streamed_data);
+
+        let streamed_data = streamed_data.unwrap();
+        let streamed_rows = streamed_data.len();
+
+        let stream_control = $chunk.instrument(($stream_processor)(streamed_data)).await;
+        $chunk.record_processor();
+        let failed = diesel_streamer::StreamControl::is_error(&stream_control);
+
+        match diesel_streamer::StreamControl::into_control_flow(stream_control) {
+            std::ops::ControlFlow::Continue(()) => {
+                std::ops::ControlFlow::Continue(streamed_rows)
+            }
+            std::ops::ControlFlow::Break(reason) => {
+                $chunk.record_break(failed);
+
+                std::ops::ControlFlow::Break(reason)
+            }
+        }
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , select = $select:expr , options = $options:expr , $stream_processor:expr) => {{
+        diesel_streamer::__async_stream_serial_table!(@stream $query, $cursor_field, $conn, [$select], $options, $stream_processor)
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , select = $select:expr , $stream_processor:expr) => {{
+        diesel_streamer::__async_stream_serial_table!(
+            @stream $query,
+            $cursor_field,
+            $conn,
+            [$select],
+            diesel_streamer::StreamOptions::new(),
+            $stream_processor
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn: expr , options = $options: expr, $stream_processor: expr) => {{
+        diesel_streamer::__async_stream_serial_table!(@stream $query, $cursor_field, $conn, [], $options, $stream_processor)
+    }};
+
     ( $query:expr ,   $cursor_field:expr ,  $conn: expr , $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__async_stream_serial_table as stream_serial_table;
 
         let default_chunk_size = 500;
         let default_from = None;
@@ -68,7 +221,7 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr , $cursor_field:expr , $conn: expr , $chunk_size: expr, $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__async_stream_serial_table as stream_serial_table;
 
         let default_from = None;
         let default_to = None;
@@ -85,7 +238,7 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr , $cursor_field:expr , $conn: expr , $chunk_size: expr, $from: expr, $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__async_stream_serial_table as stream_serial_table;
 
         let default_to = None;
 
@@ -101,45 +254,18 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr ,   $cursor_field:expr ,  $conn: expr ,  $chunk_size:expr , $from:expr, $to:expr, $stream_processor: expr) => {{
-        use diesel::dsl::{max, min};
-        use diesel::prelude::*;
-        use diesel_async::RunQueryDsl;
-
-        let mut from = match $from {
-            Some(from) => from,
-            None => $query
-                .select(min($cursor_field))
-                .get_result::<Option<i32>>($conn)
-                .await
-                .unwrap()
-                .unwrap_or(0),
-        };
-
-        let to = match $to {
-            Some(to) => to,
-            None => $query
-                .select(max($cursor_field))
-                .get_result::<Option<i32>>($conn)
-                .await
-                .unwrap()
-                .unwrap_or(0),
-        };
-
-        if (to != 0) {
-            while from <= to {
-                let chunk_limit = from + $chunk_size;
-
-                let streamed_data = $query
-                    .filter($cursor_field.eq_any(from..chunk_limit))
-                    .load($conn)
-                    .await
-                    .unwrap();
+        use diesel_streamer::__async_stream_serial_table as stream_serial_table;
 
-                ($stream_processor)(streamed_data).await;
-
-                from = chunk_limit;
-            }
-        }
+        stream_serial_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new()
+                .chunk_size($chunk_size)
+                .from($from)
+                .to($to),
+            $stream_processor
+        )
     }};
 }
 
@@ -151,14 +277,36 @@ macro_rules! stream_serial_table {
 ///
 /// - Chunk size is 500
 /// - Cursor's beginning is the minimum value of the serial table
-/// - Cursor's end is the maximum value of the serial table
+/// - Cursor's end is the maximum value of the serial table, read once
+///   before the first chunk (see [`StreamOptions::moving_upper_bound`])
+///
+/// Evaluates to a [`StreamEnd`] holding the cursor a follow-up stream
+/// should start from.
+///
+/// The processor may return `()`, `ControlFlow<B>` or `Result<(), E>` (see
+/// [`StreamControl`]). `ControlFlow::Break` and `Err` stop the stream before
+/// the next chunk is loaded and end up in [`StreamEnd::Stopped`].
+///
+/// `$query` may have a select clause of its own, which chunks are loaded
+/// with. Pass `select = ...` to load other columns instead, e.g. a tuple of
+/// columns or `Row::as_select()`: it replaces `$query`'s select clause, if
+/// any. The bounds are read with `$query`'s select clause replaced by
+/// `MIN`/`MAX` of the cursor field.
+///
+/// `$query` may be a table, a filtered query, an inner or left join, or a
+/// boxed query. The cursor field must belong to the driving table of the
+/// query, i.e. the left-hand side of its joins. `$query` is evaluated again
+/// for every statement: pass boxed queries as the expression (or function
+/// call) that builds them, and `query.clone()` for a query held in a
+/// variable.
 ///
 /// # Examples
 ///
 ///  Stream a serial table using different configurations.
 ///
 /// ```ignore
-/// use diesel_streamer::stream_serial_table;
+/// use std::sync::{atomic::AtomicBool, Arc};
+/// use diesel_streamer::{stream_serial_table, StreamOptions};
 ///
 ///
 /// async fn main() {
@@ -189,15 +337,145 @@ macro_rules! stream_serial_table {
 ///    stream_serial_table!(some_table, serial_field, conn, chunk_size, beginning_id, end_id, |streamed_table_data| {
 ///         // do work here
 ///     });
+///
+///   // with options, e.g. to also process rows inserted while streaming
+///    let options = StreamOptions::new().chunk_size(chunk_size).moving_upper_bound();
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| {
+///         // do work here
+///     });
+///
+///   // with a cancellation handle checked between chunks
+///    let cancellation = Arc::new(AtomicBool::new(false));
+///    let options = StreamOptions::new().cancellation(cancellation.clone());
+///    let stream_end = stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| {
+///         // do work here
+///     });
+///
+///    // resume later on from where the stream stopped
+///    let beginning_id = stream_end.cursor();
+///
+///   // with progress reported after each chunk
+///    let options = StreamOptions::new().on_progress(|progress| {
+///         println!("{:.1}% done, {} rows, eta {:?}", progress.percent_complete(), progress.rows, progress.eta());
+///     });
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| {
+///         // do work here
+///     });
+///
+///   // with a projection, loading only the selected columns
+///    stream_serial_table!(some_table, serial_field, conn, select = (serial_field, name), |streamed_table_data: Vec<(i32, String)>| {
+///         // do work here
+///     });
+///
+///   // with a join, by a cursor field of the driving table
+///    stream_serial_table!(some_table.inner_join(other_table), serial_field, conn, |streamed_table_data: Vec<(SomeTable, OtherTable)>| {
+///         // do work here
+///     });
+///
+///   // with only the rows of this worker's shard, e.g. worker 2 of 4
+///    let options = StreamOptions::new().shard(Shard::from_env().unwrap());
+///    stream_serial_table!(some_table, serial_field, conn, options = options, |streamed_table_data| {
+///         // do work here
+///     });
 /// }
 /// ```
-
 #[allow(clippy::module_name_repetitions)]
 #[cfg(feature = "sync")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! stream_serial_table {
+macro_rules! __sync_stream_serial_table {
+    (@stream $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:expr , $stream_processor:expr) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+
+        diesel_streamer::__stream_chunks!(;
+            $query,
+            $cursor_field,
+            $conn,
+            options,
+            100000,
+            |from, chunk_to, chunk| {
+                diesel_streamer::__sync_stream_serial_table!(
+                    @chunk $query, $cursor_field, $conn, [$($select)?], options, from, chunk_to, chunk, $stream_processor
+                )
+            }
+        )
+    }};
+
+    // Loads and processes the chunk `$from..=$chunk_to` on behalf of `@stream`
+    // and `stream_leased_table!`, with the same items in scope as `@stream`.
+    // Evaluates to a `ControlFlow` like the body of `__stream_chunks!`.
+    (@chunk $query:expr , $cursor_field:expr , $conn:expr , [$($select:expr)?] , $options:ident , $from:ident , $chunk_to:ident , $chunk:ident , $stream_processor:expr) => {{
+        let shard_filter = $options.shard.as_ref().map(|shard| {
+            diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+        });
+
+        let streamed_data = match shard_filter {
+            Some(shard_filter) => {
+                let chunk_query = $query
+                    .filter($cursor_field.eq_any($from..=$chunk_to))
+                    .filter(shard_filter)
+                    $(.select($select))?;
+                $chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                chunk_query.load($conn)
+            }
+            None => {
+                let chunk_query = $query
+                    .filter($cursor_field.eq_any($from..=$chunk_to))
+                    $(.select($select))?;
+                $chunk.trace_query(&chunk_query, backend_of(&*$conn));
+
+                chunk_query.load($conn)
+            }
+        };
+        $chunk.record_query(&streamed_data);
+
+        let streamed_data = streamed_data.unwrap();
+        let streamed_rows = streamed_data.len();
+
+        let stream_control = $chunk.in_scope(|| ($stream_processor)(streamed_data));
+        $chunk.record_processor();
+        let failed = diesel_streamer::StreamControl::is_error(&stream_control);
+
+        match diesel_streamer::StreamControl::into_control_flow(stream_control) {
+            std::ops::ControlFlow::Continue(()) => {
+                std::ops::ControlFlow::Continue(streamed_rows)
+            }
+            std::ops::ControlFlow::Break(reason) => {
+                $chunk.record_break(failed);
+
+                std::ops::ControlFlow::Break(reason)
+            }
+        }
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , select = $select:expr , options = $options:expr , $stream_processor:expr) => {{
+        diesel_streamer::__sync_stream_serial_table!(@stream $query, $cursor_field, $conn, [$select], $options, $stream_processor)
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn:expr , select = $select:expr , $stream_processor:expr) => {{
+        diesel_streamer::__sync_stream_serial_table!(
+            @stream $query,
+            $cursor_field,
+            $conn,
+            [$select],
+            diesel_streamer::StreamOptions::new(),
+            $stream_processor
+        )
+    }};
+
+    ( $query:expr , $cursor_field:expr , $conn: expr , options = $options: expr, $stream_processor: expr) => {{
+        diesel_streamer::__sync_stream_serial_table!(@stream $query, $cursor_field, $conn, [], $options, $stream_processor)
+    }};
+
     ( $query:expr ,   $cursor_field:expr ,  $conn: expr , $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__sync_stream_serial_table as stream_serial_table;
 
         let default_chunk_size = 100000;
         let default_from = None;
@@ -215,7 +493,7 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr , $cursor_field:expr , $conn: expr , $chunk_size: expr, $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__sync_stream_serial_table as stream_serial_table;
 
         let mut default_from = None;
         let default_to = None;
@@ -232,7 +510,7 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr , $cursor_field:expr , $conn: expr , $chunk_size: expr, $from: expr, $stream_processor: expr) => {{
-        use diesel_streamer::stream_serial_table;
+        use diesel_streamer::__sync_stream_serial_table as stream_serial_table;
 
         let default_to = None;
 
@@ -248,40 +526,17 @@ macro_rules! stream_serial_table {
     }};
 
     ( $query:expr , $cursor_field:expr ,  $conn: expr ,  $chunk_size:expr , $from:expr, $to:expr, $stream_processor: expr) => {{
-        use diesel::dsl::{max, min};
-        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
-
-        let mut from = match $from {
-            Some(from) => from,
-            None => $query
-                .select(min($cursor_field))
-                .get_result::<Option<i32>>($conn)
-                .unwrap()
-                .unwrap_or(0),
-        };
-
-        let to = match $to {
-            Some(to) => to,
-            None => $query
-                .select(max($cursor_field))
-                .get_result::<Option<i32>>($conn)
-                .unwrap()
-                .unwrap_or(0),
-        };
-
-        if (to != 0) {
-            while from <= to {
-                let chunk_limit = from + ($chunk_size as i32);
-
-                let streamed_data = $query
-                    .filter($cursor_field.eq_any(from..chunk_limit))
-                    .load($conn)
-                    .unwrap();
+        use diesel_streamer::__sync_stream_serial_table as stream_serial_table;
 
-                ($stream_processor)(streamed_data);
-
-                from = chunk_limit;
-            }
-        }
+        stream_serial_table!(
+            $query,
+            $cursor_field,
+            $conn,
+            options = diesel_streamer::StreamOptions::new()
+                .chunk_size($chunk_size)
+                .from($from)
+                .to($to),
+            $stream_processor
+        )
     }};
 }
@@ -0,0 +1,138 @@
+/// Implements [`Streamable`](crate::sync::Streamable) and the `stream`
+/// constructor on behalf of `#[derive(Streamable)]`.
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_impl_streamable {
+    ( row = $row:ident, table = $table:path, cursor = $cursor:path, cursor_type = $cursor_type:ty, ) => {
+        diesel_streamer::__sync_impl_streamable! {
+            row = $row,
+            table = $table,
+            cursor = $cursor,
+            cursor_type = $cursor_type,
+            connection = diesel::PgConnection,
+        }
+    };
+
+    ( row = $row:ident, table = $table:path, cursor = $cursor:path, cursor_type = $cursor_type:ty, connection = $conn:ty, ) => {
+        impl diesel_streamer::sync::Streamable<$conn> for $row {
+            type Cursor = $cursor_type;
+
+            fn stream_with<P, R>(
+                conn: &mut $conn,
+                options: diesel_streamer::StreamOptions<$cursor_type>,
+                mut processor: P,
+            ) -> diesel_streamer::StreamEnd<$cursor_type, R::Break>
+            where
+                P: FnMut(Vec<Self>) -> R,
+                R: diesel_streamer::StreamControl,
+            {
+                diesel_streamer::__sync_stream_serial_table!(
+                    $table,
+                    $cursor,
+                    conn,
+                    options = options,
+                    |rows: Vec<$row>| processor(rows)
+                )
+            }
+        }
+
+        impl $row {
+            /// Streams the table this row is loaded from.
+            pub fn stream(conn: &mut $conn) -> diesel_streamer::sync::TableStream<'_, $conn, Self> {
+                diesel_streamer::sync::TableStream::new(conn)
+            }
+        }
+    };
+}
+
+/// Implements [`Streamable`](crate::r#async::Streamable) and the
+/// `stream`/`async_stream` constructors on behalf of `#[derive(Streamable)]`.
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_impl_streamable {
+    ( row = $row:ident, table = $table:path, cursor = $cursor:path, cursor_type = $cursor_type:ty, ) => {
+        diesel_streamer::__async_impl_streamable! {
+            row = $row,
+            table = $table,
+            cursor = $cursor,
+            cursor_type = $cursor_type,
+            connection = diesel_async::AsyncPgConnection,
+        }
+    };
+
+    ( row = $row:ident, table = $table:path, cursor = $cursor:path, cursor_type = $cursor_type:ty, connection = $conn:ty, ) => {
+        impl diesel_streamer::r#async::Streamable<$conn> for $row {
+            type Cursor = $cursor_type;
+
+            fn stream_with<'c, P, F, R>(
+                conn: &'c mut $conn,
+                options: diesel_streamer::StreamOptions<$cursor_type>,
+                mut processor: P,
+            ) -> diesel_streamer::r#async::StreamEndFuture<'c, $cursor_type, R::Break>
+            where
+                P: FnMut(Vec<Self>) -> F + Send + 'c,
+                F: std::future::Future<Output = R> + Send + 'c,
+                R: diesel_streamer::StreamControl + 'c,
+            {
+                Box::pin(async move {
+                    diesel_streamer::__async_stream_serial_table!(
+                        $table,
+                        $cursor,
+                        conn,
+                        options = options,
+                        |rows: Vec<$row>| processor(rows)
+                    )
+                })
+            }
+        }
+
+        impl $row {
+            /// Streams the table this row is loaded from.
+            pub fn stream(
+                conn: &mut $conn,
+            ) -> diesel_streamer::r#async::TableStream<'_, $conn, Self> {
+                diesel_streamer::r#async::TableStream::new(conn)
+            }
+
+            /// Streams the table this row is loaded from as a
+            /// `futures::Stream` of chunks, like
+            /// `get_serial_table_async_stream!`.
+            pub fn async_stream(
+                conn: std::sync::Arc<diesel_streamer::__private::tokio::sync::Mutex<$conn>>,
+            ) -> std::pin::Pin<
+                Box<dyn diesel_streamer::__private::futures_util::Stream<Item = Vec<Self>> + Send>,
+            > {
+                let stream = diesel_streamer::get_serial_table_async_stream!(
+                    $table,
+                    $cursor,
+                    conn,
+                    std::sync::Arc<diesel_streamer::__private::tokio::sync::Mutex<$conn>>,
+                    $row,
+                    $cursor_type
+                );
+
+                Box::into_pin(stream)
+            }
+        }
+    };
+}
+
+/// Implements `Streamable` for the mode set by `#[streamable(mode = ...)]`,
+/// or for the only enabled one, on behalf of `#[derive(Streamable)]`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_streamable {
+    ( mode = sync, $($rest:tt)* ) => {
+        diesel_streamer::__sync_impl_streamable! { $($rest)* }
+    };
+
+    ( mode = async, $($rest:tt)* ) => {
+        diesel_streamer::__async_impl_streamable! { $($rest)* }
+    };
+
+    ( $($rest:tt)* ) => {
+        diesel_streamer::__default_impl_streamable! { $($rest)* }
+    };
+}
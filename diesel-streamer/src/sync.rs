@@ -0,0 +1,98 @@
+use crate::{StreamControl, StreamEnd, StreamOptions};
+
+#[cfg(feature = "dynamic")]
+#[doc(inline)]
+pub use crate::__sync_stream_dynamic_table as stream_dynamic_table;
+#[cfg(feature = "postgres")]
+#[doc(inline)]
+pub use crate::__sync_stream_leased_table as stream_leased_table;
+#[doc(inline)]
+pub use crate::{
+    __sync_copy_table as copy_table, __sync_delete_in_batches as delete_in_batches,
+    __sync_dry_run as dry_run, __sync_plan_partitions as plan_partitions,
+    __sync_stream_serial_table as stream_serial_table,
+    __sync_update_in_batches as update_in_batches,
+};
+
+/// A `Queryable` struct that knows which table and cursor field it is
+/// streamed from, usually through `#[derive(Streamable)]` (with the `derive`
+/// feature).
+///
+/// Prefer the generated `Row::stream(conn)`, which returns a [`TableStream`].
+pub trait Streamable<Conn>: Sized {
+    type Cursor;
+
+    /// Streams the table with `options`, like `stream_serial_table!`.
+    fn stream_with<P, R>(
+        conn: &mut Conn,
+        options: StreamOptions<Self::Cursor>,
+        processor: P,
+    ) -> StreamEnd<Self::Cursor, R::Break>
+    where
+        P: FnMut(Vec<Self>) -> R,
+        R: StreamControl;
+}
+
+/// Streams the table of a [`Streamable`] row, as returned by the generated
+/// `Row::stream(conn)`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let stream_end = User::stream(conn)
+///     .chunk_size(200)
+///     .from(resumed_id)
+///     .for_each(|users| {
+///         // do work here
+///     });
+/// ```
+pub struct TableStream<'c, Conn, Row: Streamable<Conn>> {
+    conn: &'c mut Conn,
+    options: StreamOptions<Row::Cursor>,
+}
+
+impl<'c, Conn, Row: Streamable<Conn>> TableStream<'c, Conn, Row> {
+    pub fn new(conn: &'c mut Conn) -> Self {
+        TableStream {
+            conn,
+            options: StreamOptions::new(),
+        }
+    }
+
+    /// Replaces the options of the stream, e.g. to set a moving upper bound
+    /// or a cancellation handle.
+    #[must_use]
+    pub fn options(mut self, options: StreamOptions<Row::Cursor>) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[must_use]
+    pub fn chunk_size(mut self, chunk_size: Row::Cursor) -> Self {
+        self.options = self.options.chunk_size(chunk_size);
+        self
+    }
+
+    /// Sets the cursor's beginning. Accepts both `5` and `Some(5)`.
+    #[must_use]
+    pub fn from(mut self, from: impl Into<Option<Row::Cursor>>) -> Self {
+        self.options = self.options.from(from);
+        self
+    }
+
+    /// Sets the cursor's end. Accepts both `50` and `Some(50)`.
+    #[must_use]
+    pub fn to(mut self, to: impl Into<Option<Row::Cursor>>) -> Self {
+        self.options = self.options.to(to);
+        self
+    }
+
+    /// Streams the table, handing each chunk to `processor`.
+    pub fn for_each<P, R>(self, processor: P) -> StreamEnd<Row::Cursor, R::Break>
+    where
+        P: FnMut(Vec<Row>) -> R,
+        R: StreamControl,
+    {
+        Row::stream_with(self.conn, self.options, processor)
+    }
+}
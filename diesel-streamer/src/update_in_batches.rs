@@ -0,0 +1,276 @@
+/// Updates a serial table in chunks, issuing one
+/// `UPDATE ... WHERE cursor >= from AND cursor <= chunk_to` per chunk
+/// instead of loading the rows.
+///
+/// Takes the table (or a filtered query on it), its serial field, a
+/// connection and anything accepted by diesel's `.set(...)`, such as an
+/// `AsChangeset` struct or `column.eq(value)`.
+///
+/// Defaults are the same as `stream_serial_table!`'s. Pass `options = ...`
+/// to configure the stream through [`StreamOptions`], including running
+/// each chunk in its own transaction with
+/// [`transaction_per_chunk`](StreamOptions::transaction_per_chunk) and
+/// [`lock_timeout`](StreamOptions::lock_timeout), or to only update the
+/// rows of a [`shard`](StreamOptions::shard).
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of updated
+/// rows, or a [`BatchError`] with the chunk whose `UPDATE` failed, e.g. on a
+/// lock timeout.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{update_in_batches, StreamOptions};
+///
+/// async fn main() {
+///     use crate::schema::users::dsl::{users, id, name};
+///
+///     let mut conn = pool.get().await.unwrap();
+///
+///     let batch_end = update_in_batches!(users, id, conn, name.eq("anonymous")).unwrap();
+///
+///     // on a subset of the table, in transactions of 1000 rows
+///     let options = StreamOptions::new()
+///         .chunk_size(1000)
+///         .lock_timeout(Duration::from_secs(2));
+///     let batch_end = update_in_batches!(users.filter(name.is_null()), id, conn, name.eq(""), options = options).unwrap();
+///
+///     println!("updated {} users", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __async_update_in_batches {
+    ( $target:expr , $cursor_field:expr , $conn:expr , $changeset:expr ) => {{
+        use diesel_streamer::__async_update_in_batches as update_in_batches;
+
+        update_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            $changeset,
+            options = diesel_streamer::StreamOptions::new()
+        )
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , $changeset:expr , options = $options:expr ) => {{
+        use diesel::prelude::*;
+        use diesel_async::scoped_futures::ScopedFutureExt;
+        use diesel_async::{AsyncConnection, RunQueryDsl};
+
+        fn backend_of<C: diesel_async::AsyncConnection>(
+            _conn: &C,
+        ) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(.await;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            500,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let updated_rows = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let update_query = diesel::update(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    )
+                    .set($changeset);
+                    // Boxed, so both arms share a type
+                    let update_query = match shard_filter {
+                        Some(shard_filter) => update_query.filter(shard_filter).into_boxed(),
+                        None => update_query.into_boxed(),
+                    };
+                    chunk.trace_query(&update_query, backend_of(&*$conn));
+
+                    let updated_rows = if options.transaction_per_chunk {
+                        let lock_timeout_statement = options.lock_timeout_statement();
+
+                        $conn
+                            .transaction::<_, diesel::result::Error, _>(|conn| {
+                                async move {
+                                    if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                        diesel::sql_query(lock_timeout_statement)
+                                            .execute(conn)
+                                            .await?;
+                                    }
+
+                                    update_query.execute(conn).await
+                                }
+                                .scope_boxed()
+                            })
+                            .await
+                    } else {
+                        update_query.execute($conn).await
+                    };
+                    chunk.record_execute(&updated_rows);
+
+                    match updated_rows {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        updated_rows => break updated_rows,
+                    }
+                };
+
+                match updated_rows {
+                    Ok(updated_rows) => {
+                        affected_rows += updated_rows;
+
+                        std::ops::ControlFlow::Continue(updated_rows)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+}
+
+/// Updates a serial table in chunks, issuing one
+/// `UPDATE ... WHERE cursor >= from AND cursor <= chunk_to` per chunk
+/// instead of loading the rows.
+///
+/// Takes the table (or a filtered query on it), its serial field, a
+/// connection and anything accepted by diesel's `.set(...)`, such as an
+/// `AsChangeset` struct or `column.eq(value)`.
+///
+/// Defaults are the same as `stream_serial_table!`'s. Pass `options = ...`
+/// to configure the stream through [`StreamOptions`], including running
+/// each chunk in its own transaction with
+/// [`transaction_per_chunk`](StreamOptions::transaction_per_chunk) and
+/// [`lock_timeout`](StreamOptions::lock_timeout), or to only update the
+/// rows of a [`shard`](StreamOptions::shard).
+///
+/// Evaluates to a `Result` holding a [`BatchEnd`] with the number of updated
+/// rows, or a [`BatchError`] with the chunk whose `UPDATE` failed, e.g. on a
+/// lock timeout.
+///
+/// # Examples
+///
+/// ```ignore
+/// use diesel_streamer::{update_in_batches, StreamOptions};
+///
+/// fn main() {
+///     use crate::schema::users::dsl::{users, id, name};
+///
+///     let mut conn = pool.get().unwrap();
+///
+///     let batch_end = update_in_batches!(users, id, conn, name.eq("anonymous")).unwrap();
+///
+///     // on a subset of the table, in transactions of 1000 rows
+///     let options = StreamOptions::new()
+///         .chunk_size(1000)
+///         .lock_timeout(Duration::from_secs(2));
+///     let batch_end = update_in_batches!(users.filter(name.is_null()), id, conn, name.eq(""), options = options).unwrap();
+///
+///     println!("updated {} users", batch_end.affected_rows);
+/// }
+/// ```
+#[cfg(feature = "sync")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sync_update_in_batches {
+    ( $target:expr , $cursor_field:expr , $conn:expr , $changeset:expr ) => {{
+        use diesel_streamer::__sync_update_in_batches as update_in_batches;
+
+        update_in_batches!(
+            $target,
+            $cursor_field,
+            $conn,
+            $changeset,
+            options = diesel_streamer::StreamOptions::new()
+        )
+    }};
+
+    ( $target:expr , $cursor_field:expr , $conn:expr , $changeset:expr , options = $options:expr ) => {{
+        use diesel::{prelude::*, QueryDsl, RunQueryDsl};
+
+        fn backend_of<C: diesel::Connection>(_conn: &C) -> std::marker::PhantomData<C::Backend> {
+            std::marker::PhantomData
+        }
+
+        let mut options = $options;
+        let mut affected_rows = 0;
+
+        let stream_end = diesel_streamer::__stream_chunks!(;
+            $target,
+            $cursor_field,
+            $conn,
+            options,
+            100000,
+            |from, chunk_to, chunk| {
+                let mut attempts = 0;
+
+                let updated_rows = loop {
+                    let shard_filter = options.shard.as_ref().map(|shard| {
+                        diesel_streamer::__shard_filter(&$cursor_field, backend_of(&*$conn), shard)
+                    });
+
+                    let update_query = diesel::update(
+                        $target.filter($cursor_field.ge(from).and($cursor_field.le(chunk_to))),
+                    )
+                    .set($changeset);
+                    // Boxed, so both arms share a type
+                    let update_query = match shard_filter {
+                        Some(shard_filter) => update_query.filter(shard_filter).into_boxed(),
+                        None => update_query.into_boxed(),
+                    };
+                    chunk.trace_query(&update_query, backend_of(&*$conn));
+
+                    let updated_rows = if options.transaction_per_chunk {
+                        let lock_timeout_statement = options.lock_timeout_statement();
+
+                        $conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                            if let Some(lock_timeout_statement) = lock_timeout_statement {
+                                diesel::sql_query(lock_timeout_statement).execute(conn)?;
+                            }
+
+                            update_query.execute(conn)
+                        })
+                    } else {
+                        update_query.execute($conn)
+                    };
+                    chunk.record_execute(&updated_rows);
+
+                    match updated_rows {
+                        Err(_) if attempts < options.retries => {
+                            attempts += 1;
+                            chunk.record_retry(attempts);
+                        }
+                        updated_rows => break updated_rows,
+                    }
+                };
+
+                match updated_rows {
+                    Ok(updated_rows) => {
+                        affected_rows += updated_rows;
+
+                        std::ops::ControlFlow::Continue(updated_rows)
+                    }
+                    Err(error) => std::ops::ControlFlow::Break((
+                        chunk_to,
+                        diesel_streamer::__ChunkError::<std::convert::Infallible>::Query(error),
+                    )),
+                }
+            }
+        );
+
+        diesel_streamer::__batch_result(affected_rows, stream_end)
+    }};
+}